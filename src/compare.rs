@@ -0,0 +1,312 @@
+//! Value by value comparison of two reports.
+
+use crate::SubFingerprint;
+use crate::report::{Report, TestReport};
+use crate::suite::{ResultKind, find_test};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comparison {
+    pub tests: Vec<TestComparison>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestComparison {
+    pub id: String,
+    pub name: String,
+    pub status: TestStatus,
+    /// Number of results on each side.
+    pub lengths: (usize, usize),
+    /// Names of the sub-fingerprints that differ, see [`crate::FingerprintTest::sub_results`],
+    /// and of the core classes that differ on hybrid CPUs.
+    pub differing_parts: Vec<String>,
+    /// Results that differ, over the indices both sides have.
+    pub differences: Vec<ResultDifference>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestStatus {
+    Match,
+    Different,
+    /// The test changed between the two builds, results can't be compared.
+    VersionMismatch(u32, u32),
+    OnlyInLeft,
+    OnlyInRight,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultDifference {
+    pub index: usize,
+    /// The input that produced this result, see [`crate::FingerprintTest::describe_recorded_result`].
+    pub input: Option<String>,
+    pub left: f64,
+    pub right: f64,
+    pub kind: ResultKind,
+    /// `None` when either side is NaN or the result isn't [`ResultKind::Numeric`].
+    pub ulps: Option<u64>,
+    /// Known when both reports were made in attribution mode.
+    pub cause: Option<Cause>,
+}
+
+/// Where a difference comes from, decided by re-running the test on the portable math library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cause {
+    /// The difference disappears with portable math, the system libm produced it.
+    Library,
+    /// The difference persists with portable math, so the arithmetic itself differs.
+    Hardware,
+}
+
+impl Comparison {
+    pub fn all_match(&self) -> bool {
+        self.tests
+            .iter()
+            .all(|test| test.status == TestStatus::Match)
+    }
+}
+
+/// Compares every test present in either report.
+pub fn compare_reports(left: &Report, right: &Report) -> Comparison {
+    let mut tests: Vec<TestComparison> = left
+        .tests
+        .iter()
+        .map(|left_test| match right.test(&left_test.id) {
+            Some(right_test) => compare_tests(left_test, right_test, left.sample_size),
+            None => missing(left_test, TestStatus::OnlyInLeft),
+        })
+        .collect();
+
+    tests.extend(
+        right
+            .tests
+            .iter()
+            .filter(|right_test| left.test(&right_test.id).is_none())
+            .map(|right_test| missing(right_test, TestStatus::OnlyInRight)),
+    );
+
+    Comparison { tests }
+}
+
+fn missing(test: &TestReport, status: TestStatus) -> TestComparison {
+    TestComparison {
+        id: test.id.clone(),
+        name: test.name.clone(),
+        status,
+        lengths: match status {
+            TestStatus::OnlyInLeft => (test.raw_results.len(), 0),
+            _ => (0, test.raw_results.len()),
+        },
+        differing_parts: Vec::new(),
+        differences: Vec::new(),
+    }
+}
+
+fn compare_tests(left: &TestReport, right: &TestReport, sample_size: usize) -> TestComparison {
+    let mut comparison = TestComparison {
+        id: left.id.clone(),
+        name: left.name.clone(),
+        status: TestStatus::Match,
+        lengths: (left.raw_results.len(), right.raw_results.len()),
+        differing_parts: Vec::new(),
+        differences: Vec::new(),
+    };
+
+    if left.version != right.version {
+        comparison.status = TestStatus::VersionMismatch(left.version, right.version);
+        return comparison;
+    }
+
+    // on hybrid CPUs the first run landed on whichever core the scheduler picked, the composite
+    // of the pinned runs doesn't
+    let (left_fingerprint, right_fingerprint) =
+        match (&left.composite_fingerprint, &right.composite_fingerprint) {
+            (Some(l), Some(r)) => (l, r),
+            _ => (&left.fingerprint, &right.fingerprint),
+        };
+
+    if left_fingerprint == right_fingerprint {
+        return comparison;
+    }
+
+    comparison.status = TestStatus::Different;
+    comparison.differing_parts = differing(&left.sub_fingerprints, &right.sub_fingerprints)
+        .map(|name| name.to_string())
+        .chain(
+            differing(&left.core_classes, &right.core_classes)
+                .map(|name| format!("{} cores", name)),
+        )
+        .collect();
+
+    let test = find_test(&left.id, sample_size);
+    let portable = left.portable.as_ref().zip(right.portable.as_ref());
+
+    for (index, (&l, &r)) in left
+        .raw_results
+        .iter()
+        .zip(right.raw_results.iter())
+        .enumerate()
+    {
+        if l.to_bits() != r.to_bits() {
+            let kind = test
+                .as_ref()
+                .map_or(ResultKind::Numeric, |test| test.result_kind(index));
+
+            comparison.differences.push(ResultDifference {
+                index,
+                input: test
+                    .as_ref()
+                    .ok()
+                    .and_then(|test| test.describe_recorded_result(&left.raw_results, index)),
+                left: l,
+                right: r,
+                kind,
+                ulps: match kind {
+                    ResultKind::Numeric => ulp_distance(l, r),
+                    ResultKind::Category | ResultKind::Bits => None,
+                },
+                cause: portable.map(|(left, right)| {
+                    let l = left.raw_results.get(index).map(|val| val.to_bits());
+                    let r = right.raw_results.get(index).map(|val| val.to_bits());
+
+                    if l == r {
+                        Cause::Library
+                    } else {
+                        Cause::Hardware
+                    }
+                }),
+            });
+        }
+    }
+
+    comparison
+}
+
+/// Names of the parts of `left` that `right` doesn't have with the same fingerprint.
+fn differing<'a>(
+    left: &'a [SubFingerprint],
+    right: &'a [SubFingerprint],
+) -> impl Iterator<Item = &'a str> {
+    left.iter()
+        .filter(|part| {
+            !right
+                .iter()
+                .any(|other| other.name == part.name && other.fingerprint == part.fingerprint)
+        })
+        .map(|part| part.name.as_str())
+}
+
+/// Number of representable `f64` values between `a` and `b`, `None` if either is NaN.
+///
+/// `0.0` and `-0.0` are one ulp apart, so a sign flip of zero still shows up.
+pub fn ulp_distance(a: f64, b: f64) -> Option<u64> {
+    if a.is_nan() || b.is_nan() {
+        return None;
+    }
+
+    Some(ordered_bits(a).abs_diff(ordered_bits(b)))
+}
+
+// Maps the bits onto a line where adjacent floats are adjacent integers, -0.0 sits just below 0.0
+fn ordered_bits(val: f64) -> i64 {
+    let bits = val.to_bits() as i64;
+
+    if bits < 0 {
+        -(bits & i64::MAX) - 1
+    } else {
+        bits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FingerprintTest;
+    use crate::suite::DenormalTimingTest;
+
+    fn report_with(results: Vec<f64>) -> Report {
+        report_of(&DenormalTimingTest::default(), results)
+    }
+
+    fn report_of(test: &dyn FingerprintTest, results: Vec<f64>) -> Report {
+        let mut test = TestReport::new(test);
+        test.fingerprint = format!("{:?}", results);
+        test.raw_results = results;
+
+        let mut report = Report::new(1, crate::SAMPLE_SIZE);
+        report.tests.push(test);
+        report
+    }
+
+    #[test]
+    fn categorical_results_have_no_ulp_distance() {
+        let comparison = compare_reports(&report_with(vec![2.0]), &report_with(vec![4.0]));
+        let difference = &comparison.tests[0].differences[0];
+
+        assert_eq!(difference.kind, ResultKind::Category);
+        assert_eq!(difference.ulps, None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn changed_latency_pairs_are_named_from_the_recorded_cpus() {
+        use crate::suite::CoreLatencyTest;
+
+        // 2 tiers over CPUs 0, 2 and 5, then the tiers of 0-2, 0-5 and 2-5
+        let left = report_of(
+            &CoreLatencyTest::default(),
+            vec![2.0, 3.0, 0.0, 2.0, 5.0, 0.0, 1.0, 1.0],
+        );
+        let right = report_of(
+            &CoreLatencyTest::default(),
+            vec![2.0, 3.0, 0.0, 2.0, 5.0, 0.0, 1.0, 0.0],
+        );
+
+        let comparison = compare_reports(&left, &right);
+        let difference = &comparison.tests[0].differences[0];
+
+        assert_eq!(difference.index, 7);
+        assert_eq!(
+            difference.input.as_deref(),
+            Some("latency tier of CPU 2 and CPU 5")
+        );
+    }
+
+    fn hybrid_report(first_run: f64, efficiency: &str) -> Report {
+        let mut report = report_with(vec![first_run]);
+        let test = &mut report.tests[0];
+        test.core_classes = vec![
+            SubFingerprint {
+                name: "efficiency".to_string(),
+                fingerprint: efficiency.to_string(),
+            },
+            SubFingerprint {
+                name: "performance".to_string(),
+                fingerprint: "p".to_string(),
+            },
+        ];
+        test.composite_fingerprint = Some(format!("p+{}", efficiency));
+        report
+    }
+
+    #[test]
+    fn hybrid_reports_compare_by_composite() {
+        // the first runs landed on different core classes
+        let comparison = compare_reports(&hybrid_report(2.0, "e"), &hybrid_report(3.0, "e"));
+        assert!(comparison.all_match());
+
+        let comparison = compare_reports(&hybrid_report(2.0, "e"), &hybrid_report(2.0, "x"));
+        assert_eq!(comparison.tests[0].status, TestStatus::Different);
+        assert_eq!(comparison.tests[0].differing_parts, ["efficiency cores"]);
+    }
+
+    #[test]
+    fn ulp_distance_counts_representable_values() {
+        assert_eq!(ulp_distance(1.0, 1.0 + f64::EPSILON), Some(1));
+        assert_eq!(ulp_distance(-0.0, 0.0), Some(1));
+        // every subnormal on both sides, plus the step from -0.0 to 0.0
+        assert_eq!(
+            ulp_distance(-f64::MIN_POSITIVE, f64::MIN_POSITIVE),
+            Some((2 << 52) + 1)
+        );
+        assert_eq!(ulp_distance(f64::NAN, 1.0), None);
+    }
+}
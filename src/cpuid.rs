@@ -0,0 +1,281 @@
+//! Identity of the processor as reported by the CPUID instruction.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CpuIdentity {
+    /// e.g. `"GenuineIntel"` or `"AuthenticAMD"`.
+    pub vendor: String,
+    /// e.g. `"AMD Ryzen 7 5800X 8-Core Processor"`, empty if the CPU has no brand string.
+    pub brand: String,
+    /// Leaf 1 EAX, the packed family/model/stepping.
+    pub signature: u32,
+    /// Family with the extended family added in.
+    pub family: u32,
+    /// Model with the extended model added in.
+    pub model: u32,
+    pub stepping: u32,
+    /// Names of the supported features from [`FEATURES`], in table order.
+    pub features: Vec<String>,
+    pub caches: Vec<Cache>,
+    /// Set when running under a hypervisor, which may hide or fake any of the above.
+    pub hypervisor: bool,
+    /// Vendor of the hypervisor from leaf 0x40000000, e.g. `"KVMKVMKVM"`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hypervisor_vendor: Option<String>,
+}
+
+/// One cache from the deterministic cache parameters (leaf 4, or 0x8000001D on AMD).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Cache {
+    pub level: u32,
+    pub kind: CacheKind,
+    pub size: u64,
+    pub ways: u32,
+    pub line_size: u32,
+    pub sets: u32,
+    /// Maximum number of logical processors sharing this cache.
+    pub shared_by: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CacheKind {
+    Data,
+    Instruction,
+    Unified,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    Ebx,
+    Ecx,
+    Edx,
+}
+
+/// Feature flags recorded in [`CpuIdentity::features`]: leaf, sub-leaf, register, bit and name.
+pub const FEATURES: [(u32, u32, Register, u32, &str); 34] = [
+    (1, 0, Register::Edx, 0, "x87"),
+    (1, 0, Register::Edx, 4, "tsc"),
+    (1, 0, Register::Edx, 25, "sse"),
+    (1, 0, Register::Edx, 26, "sse2"),
+    (1, 0, Register::Ecx, 0, "sse3"),
+    (1, 0, Register::Ecx, 9, "ssse3"),
+    (1, 0, Register::Ecx, 12, "fma"),
+    (1, 0, Register::Ecx, 19, "sse4.1"),
+    (1, 0, Register::Ecx, 20, "sse4.2"),
+    (1, 0, Register::Ecx, 23, "popcnt"),
+    (1, 0, Register::Ecx, 25, "aes"),
+    (1, 0, Register::Ecx, 28, "avx"),
+    (1, 0, Register::Ecx, 29, "f16c"),
+    (1, 0, Register::Ecx, 30, "rdrand"),
+    (7, 0, Register::Ebx, 3, "bmi1"),
+    (7, 0, Register::Ebx, 5, "avx2"),
+    (7, 0, Register::Ebx, 8, "bmi2"),
+    (7, 0, Register::Ebx, 16, "avx512f"),
+    (7, 0, Register::Ebx, 17, "avx512dq"),
+    (7, 0, Register::Ebx, 19, "adx"),
+    (7, 0, Register::Ebx, 21, "avx512ifma"),
+    (7, 0, Register::Ebx, 28, "avx512cd"),
+    (7, 0, Register::Ebx, 29, "sha"),
+    (7, 0, Register::Ebx, 30, "avx512bw"),
+    (7, 0, Register::Ebx, 31, "avx512vl"),
+    (7, 0, Register::Ecx, 1, "avx512vbmi"),
+    (7, 0, Register::Ecx, 11, "avx512vnni"),
+    (7, 0, Register::Edx, 15, "hybrid"),
+    (7, 0, Register::Edx, 23, "avx512fp16"),
+    (7, 1, Register::Ecx, 4, "avx-vnni"),
+    (0x8000_0001, 0, Register::Ecx, 5, "lzcnt"),
+    (0x8000_0001, 0, Register::Ecx, 6, "sse4a"),
+    (0x8000_0001, 0, Register::Ecx, 11, "xop"),
+    (0x8000_0001, 0, Register::Ecx, 16, "fma4"),
+];
+
+/// Reads the identity of the CPU this thread runs on, `None` where there is no CPUID.
+pub fn identify() -> Option<CpuIdentity> {
+    #[cfg(target_arch = "x86_64")]
+    return Some(x86::identify());
+
+    #[cfg(not(target_arch = "x86_64"))]
+    return None;
+}
+
+/// Core type of the CPU this thread runs on from leaf 0x1A, `None` unless the CPU is hybrid.
+/// `0x40` is a performance (Core) core, `0x20` an efficiency (Atom) core.
+pub fn hybrid_core_type() -> Option<u8> {
+    #[cfg(target_arch = "x86_64")]
+    return x86::hybrid_core_type();
+
+    #[cfg(not(target_arch = "x86_64"))]
+    return None;
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::{__cpuid_count, CpuidResult};
+
+    use super::{Cache, CacheKind, CpuIdentity, FEATURES, Register};
+
+    const EXTENDED: u32 = 0x8000_0000;
+    const HYPERVISOR: u32 = 0x4000_0000;
+
+    struct Cpuid {
+        max_basic: u32,
+        max_extended: u32,
+    }
+
+    impl Cpuid {
+        fn new() -> Self {
+            Self {
+                max_basic: raw(0, 0).eax,
+                max_extended: raw(EXTENDED, 0).eax,
+            }
+        }
+
+        // An all zero result for leaves the CPU doesn't have
+        fn leaf(&self, leaf: u32, subleaf: u32) -> CpuidResult {
+            let max = if leaf >= EXTENDED {
+                self.max_extended
+            } else {
+                self.max_basic
+            };
+
+            if leaf > max {
+                CpuidResult {
+                    eax: 0,
+                    ebx: 0,
+                    ecx: 0,
+                    edx: 0,
+                }
+            } else {
+                raw(leaf, subleaf)
+            }
+        }
+    }
+
+    fn raw(leaf: u32, subleaf: u32) -> CpuidResult {
+        __cpuid_count(leaf, subleaf)
+    }
+
+    fn ascii(registers: &[u32]) -> String {
+        let bytes: Vec<u8> = registers.iter().flat_map(|reg| reg.to_le_bytes()).collect();
+        String::from_utf8_lossy(&bytes)
+            .trim_matches(|c: char| c == '\0' || c.is_whitespace())
+            .to_string()
+    }
+
+    pub fn hybrid_core_type() -> Option<u8> {
+        let cpuid = Cpuid::new();
+        let hybrid = cpuid.leaf(7, 0).edx & (1 << 15) != 0;
+
+        (hybrid && cpuid.max_basic >= 0x1a).then(|| (cpuid.leaf(0x1a, 0).eax >> 24) as u8)
+    }
+
+    pub fn identify() -> CpuIdentity {
+        let cpuid = Cpuid::new();
+
+        let vendor_leaf = cpuid.leaf(0, 0);
+        let vendor = ascii(&[vendor_leaf.ebx, vendor_leaf.edx, vendor_leaf.ecx]);
+
+        let brand = if cpuid.max_extended >= EXTENDED + 4 {
+            let parts: Vec<u32> = (2..=4)
+                .flat_map(|i| {
+                    let r = cpuid.leaf(EXTENDED + i, 0);
+                    [r.eax, r.ebx, r.ecx, r.edx]
+                })
+                .collect();
+            ascii(&parts)
+        } else {
+            String::new()
+        };
+
+        let signature = cpuid.leaf(1, 0).eax;
+        let base_family = (signature >> 8) & 0xf;
+        let base_model = (signature >> 4) & 0xf;
+
+        let family = if base_family == 0xf {
+            base_family + ((signature >> 20) & 0xff)
+        } else {
+            base_family
+        };
+        let model = if base_family == 0x6 || base_family == 0xf {
+            base_model | ((signature >> 16) & 0xf) << 4
+        } else {
+            base_model
+        };
+
+        let features = FEATURES
+            .iter()
+            .filter(|&&(leaf, subleaf, register, bit, _)| {
+                let r = cpuid.leaf(leaf, subleaf);
+                let val = match register {
+                    Register::Ebx => r.ebx,
+                    Register::Ecx => r.ecx,
+                    Register::Edx => r.edx,
+                };
+                val & (1 << bit) != 0
+            })
+            .map(|&(.., name)| name.to_string())
+            .collect();
+
+        let hypervisor = cpuid.leaf(1, 0).ecx & (1 << 31) != 0;
+        let hypervisor_vendor = hypervisor.then(|| {
+            let r = raw(HYPERVISOR, 0);
+            ascii(&[r.ebx, r.ecx, r.edx])
+        });
+
+        CpuIdentity {
+            vendor,
+            brand,
+            signature,
+            family,
+            model,
+            stepping: signature & 0xf,
+            features,
+            caches: caches(&cpuid),
+            hypervisor,
+            hypervisor_vendor,
+        }
+    }
+
+    fn caches(cpuid: &Cpuid) -> Vec<Cache> {
+        // AMD reports the same layout in 0x8000001D when it has topology extensions
+        let topology_extensions = cpuid.leaf(EXTENDED + 1, 0).ecx & (1 << 22) != 0;
+        let leaf = if topology_extensions {
+            EXTENDED + 0x1d
+        } else {
+            4
+        };
+
+        let mut caches = Vec::new();
+
+        // 16 is far more than any CPU has, it only guards against a broken hypervisor
+        for subleaf in 0..16 {
+            let r = cpuid.leaf(leaf, subleaf);
+
+            let kind = match r.eax & 0x1f {
+                1 => CacheKind::Data,
+                2 => CacheKind::Instruction,
+                3 => CacheKind::Unified,
+                _ => break,
+            };
+
+            let line_size = (r.ebx & 0xfff) + 1;
+            let partitions = ((r.ebx >> 12) & 0x3ff) + 1;
+            let ways = (r.ebx >> 22) + 1;
+            let sets = r.ecx + 1;
+
+            caches.push(Cache {
+                level: (r.eax >> 5) & 0x7,
+                kind,
+                size: ways as u64 * partitions as u64 * line_size as u64 * sets as u64,
+                ways,
+                line_size,
+                sets,
+                shared_by: ((r.eax >> 14) & 0xfff) + 1,
+            });
+        }
+
+        caches
+    }
+}
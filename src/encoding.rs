@@ -0,0 +1,137 @@
+//! Canonical byte encoding of a test's results, shared by the fingerprint hash and raw dumps.
+//!
+//! A record is laid out as, all integers little-endian:
+//!
+//! | field        | size              |
+//! |--------------|-------------------|
+//! | id length    | `u32`             |
+//! | test id      | id length, UTF-8  |
+//! | value count  | `u64`             |
+//! | values       | count × `u64`, the `f64::to_bits` of each result |
+//!
+//! The encoding is independent of the host byte order, so a fingerprint is defined purely by the
+//! numeric values. Records are self-delimiting and can be concatenated in one file.
+
+use std::io::{self, Read, Write};
+
+/// The decoded form of one record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultRecord {
+    pub test_id: String,
+    pub results: Vec<f64>,
+}
+
+/// Writes the canonical record for `results` of the test `test_id`.
+pub fn write_results<W: Write>(writer: &mut W, test_id: &str, results: &[f64]) -> io::Result<()> {
+    let id_len = u32::try_from(test_id.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "test id too long"))?;
+
+    writer.write_all(&id_len.to_le_bytes())?;
+    writer.write_all(test_id.as_bytes())?;
+    writer.write_all(&(results.len() as u64).to_le_bytes())?;
+
+    for val in results {
+        writer.write_all(&val.to_bits().to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// The canonical record as a byte vector.
+pub fn encode_results(test_id: &str, results: &[f64]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(4 + test_id.len() + 8 + results.len() * 8);
+    write_results(&mut bytes, test_id, results).expect("writing to a Vec can't fail");
+    bytes
+}
+
+/// Reads the next record, `Ok(None)` on a clean end of input.
+pub fn read_results<R: Read>(reader: &mut R) -> io::Result<Option<ResultRecord>> {
+    let mut id_len = [0; 4];
+    match reader.read(&mut id_len[..1])? {
+        0 => return Ok(None),
+        _ => reader.read_exact(&mut id_len[1..])?,
+    }
+
+    let mut test_id = vec![0; u32::from_le_bytes(id_len) as usize];
+    reader.read_exact(&mut test_id)?;
+    let test_id = String::from_utf8(test_id)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "test id is not UTF-8"))?;
+
+    let mut count = [0; 8];
+    reader.read_exact(&mut count)?;
+    let count = u64::from_le_bytes(count);
+
+    let mut results = Vec::new();
+    let mut bits = [0; 8];
+    for _ in 0..count {
+        reader.read_exact(&mut bits)?;
+        results.push(f64::from_bits(u64::from_le_bytes(bits)));
+    }
+
+    Ok(Some(ResultRecord { test_id, results }))
+}
+
+/// Reads every record until the end of input.
+pub fn read_all_results<R: Read>(reader: &mut R) -> io::Result<Vec<ResultRecord>> {
+    let mut records = Vec::new();
+    while let Some(record) = read_results(reader)? {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn golden_byte_layout() {
+        let bytes = encode_results("ab", &[1.0, -0.0]);
+
+        #[rustfmt::skip]
+        let expected = [
+            2, 0, 0, 0,
+            b'a', b'b',
+            2, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 0, 0, 0xf0, 0x3f,
+            0, 0, 0, 0, 0, 0, 0, 0x80,
+        ];
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let nan = f64::from_bits(0x7ff8_0000_dead_beef);
+        let mut bytes = Vec::new();
+        write_results(&mut bytes, "first", &[0.1, f64::MIN_POSITIVE / 3.0, nan]).unwrap();
+        write_results(&mut bytes, "empty", &[]).unwrap();
+
+        let records = read_all_results(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].test_id, "first");
+        let bits: Vec<u64> = records[0].results.iter().map(|r| r.to_bits()).collect();
+        assert_eq!(
+            bits,
+            [
+                0.1f64.to_bits(),
+                (f64::MIN_POSITIVE / 3.0).to_bits(),
+                nan.to_bits()
+            ]
+        );
+        assert_eq!(
+            records[1],
+            ResultRecord {
+                test_id: "empty".to_string(),
+                results: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn truncated_record_is_an_error() {
+        let bytes = encode_results("test", &[1.0]);
+
+        assert!(read_results(&mut &bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+use crate::report::ReportError;
+
+/// Everything that can go wrong while fingerprinting or handling reports.
+#[derive(Debug)]
+pub enum FingerprintError {
+    /// Reading or writing `path` failed.
+    Io { path: PathBuf, source: io::Error },
+    /// No registered test has this id.
+    UnknownTest(String),
+    /// The test needs an instruction set extension this CPU doesn't have.
+    UnsupportedCpuFeature { test: String, feature: String },
+    /// A parameter is out of range, e.g. a sample size too small for a test.
+    InvalidConfiguration(String),
+    /// A report file couldn't be parsed.
+    InvalidReport { path: PathBuf, source: ReportError },
+    /// A thread couldn't be pinned to logical CPU `cpu`, or with no `cpu` the allowed CPUs
+    /// couldn't be read.
+    Affinity {
+        cpu: Option<usize>,
+        source: io::Error,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, FingerprintError>;
+
+impl FingerprintError {
+    pub fn io(path: impl Into<PathBuf>, source: io::Error) -> Self {
+        Self::Io {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for FingerprintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+            Self::UnknownTest(id) => write!(f, "unknown test `{}`", id),
+            Self::UnsupportedCpuFeature { test, feature } => {
+                write!(f, "test `{}` requires the `{}` CPU feature", test, feature)
+            }
+            Self::InvalidConfiguration(reason) => write!(f, "invalid configuration: {}", reason),
+            Self::InvalidReport { path, source } => {
+                write!(f, "{} is not a valid report: {}", path.display(), source)
+            }
+            Self::Affinity {
+                cpu: Some(cpu),
+                source,
+            } => write!(f, "pinning to CPU {} failed: {}", cpu, source),
+            Self::Affinity { cpu: None, source } => {
+                write!(f, "reading the CPU affinity failed: {}", source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FingerprintError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::InvalidReport { source, .. } => Some(source),
+            Self::Affinity { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
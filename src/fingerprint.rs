@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use crate::FingerprintTest;
+use crate::encoding::write_results;
+use crate::sha256::{Sha256, to_hex};
+
+/// Prefix of every fingerprint, changes whenever the hash or its input encoding changes.
+pub const FINGERPRINT_PREFIX: &str = "v3-sha256:";
+
+/// Fingerprint of a named subset of a test's results, see [`FingerprintTest::sub_results`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubFingerprint {
+    pub name: String,
+    pub fingerprint: String,
+}
+
+/// Hashes the exact bit patterns of `results`, so any difference in the last ulp changes the fingerprint.
+///
+/// The input to SHA-256 is the canonical record from [`crate::encoding`], the output is
+/// [`FINGERPRINT_PREFIX`] followed by the hex digest.
+pub fn calculate_fingerprint_full_precision(test_id: &str, results: &[f64]) -> String {
+    let mut hasher = Sha256::new();
+
+    write_results(&mut hasher, test_id, results).expect("hashing can't fail");
+
+    format!("{}{}", FINGERPRINT_PREFIX, to_hex(&hasher.finish()))
+}
+
+/// Hashes a list of sub-fingerprints into one.
+///
+/// The input to SHA-256 is the test id followed by each name and fingerprint, every string
+/// prefixed with its length as a little-endian `u32`.
+pub fn combine_fingerprints(test_id: &str, parts: &[SubFingerprint]) -> String {
+    let mut hasher = Sha256::new();
+
+    let mut write_str = |s: &str| {
+        hasher.update(&(s.len() as u32).to_le_bytes());
+        hasher.update(s.as_bytes());
+    };
+
+    write_str(test_id);
+    for part in parts {
+        write_str(&part.name);
+        write_str(&part.fingerprint);
+    }
+
+    format!("{}{}", FINGERPRINT_PREFIX, to_hex(&hasher.finish()))
+}
+
+/// Fingerprints one run of `test`, returning the test fingerprint and its sub-fingerprints.
+pub fn fingerprint_test(
+    test: &dyn FingerprintTest,
+    results: &[f64],
+) -> (String, Vec<SubFingerprint>) {
+    let parts: Vec<SubFingerprint> = test
+        .sub_results(results)
+        .into_iter()
+        .map(|(name, values)| SubFingerprint {
+            fingerprint: calculate_fingerprint_full_precision(
+                &format!("{}/{}", test.id(), name),
+                &values,
+            ),
+            name,
+        })
+        .collect();
+
+    if parts.is_empty() {
+        (
+            calculate_fingerprint_full_precision(test.id(), results),
+            parts,
+        )
+    } else {
+        (combine_fingerprints(test.id(), &parts), parts)
+    }
+}
@@ -0,0 +1,52 @@
+//! Self-check for results the compiler computed at build time instead of on this CPU.
+//!
+//! LLVM folds arithmetic and libm calls on constant inputs using the build host's math, which
+//! would fingerprint the compiler machine. The tests hide their inputs behind
+//! [`std::hint::black_box`]; this check evaluates a few representative expressions both ways to
+//! show whether folding would have changed anything.
+
+use std::hint::black_box;
+
+/// An expression that gave a different result when its input was a compile time constant.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FoldedResult {
+    pub expression: &'static str,
+    /// Result with a literal input, possibly computed at build time.
+    pub folded: f64,
+    /// Result with the input hidden from the optimizer.
+    pub runtime: f64,
+}
+
+#[inline(always)]
+fn evaluate(expression: &'static str, input: f64, f: impl Fn(f64) -> f64) -> FoldedResult {
+    FoldedResult {
+        expression,
+        folded: f(input),
+        runtime: f(black_box(input)),
+    }
+}
+
+/// Every checked expression whose folded and runtime results differ, empty when none do.
+pub fn folded_results() -> Vec<FoldedResult> {
+    [
+        evaluate("1e-308 / 1.1123156", 1e-308, |x| x / 1.1123156),
+        evaluate("1e-320 * 0.9123545676", 1e-320, |x| x * 0.9123545676),
+        evaluate("2e-308 * 0.951235467 + 2e-308 / 1.05123245", 2e-308, |x| {
+            x * 0.951235467 + x / 1.05123245
+        }),
+        evaluate("sin(0.5)", 0.5, f64::sin),
+        evaluate("sin(1e22)", 1e22, f64::sin),
+        evaluate("cos(3.5)", 3.5, f64::cos),
+        evaluate("atan(1e-5)", 1e-5, f64::atan),
+        evaluate("exp(0.7)", 0.7, f64::exp),
+        evaluate("sinh(2.5)", 2.5, f64::sinh),
+        evaluate("cosh(2.5)", 2.5, f64::cosh),
+        evaluate("tanh(0.3)", 0.3, f64::tanh),
+        evaluate("log10(1.75)", 1.75, f64::log10),
+        evaluate("log2(3.3)", 3.3, f64::log2),
+        evaluate("hypot(sin(0.9), cos(0.9))", 0.9, |x| x.sin().hypot(x.cos())),
+    ]
+    .into_iter()
+    .filter(|result| result.folded.to_bits() != result.runtime.to_bits())
+    .collect()
+}
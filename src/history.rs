@@ -0,0 +1,296 @@
+//! Local log of past runs, to tell what changed in between when fingerprints change.
+//!
+//! One JSON object per line, appended after every run.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{FingerprintError, Result};
+use crate::platform::microcode_summary;
+use crate::report::Report;
+
+/// Default history path, next to the default report names.
+pub const HISTORY_FILE: &str = "fingerprint_history.jsonl";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub tool_version: String,
+    pub sample_size: usize,
+    /// Distinct microcode revisions, see [`microcode_summary`].
+    pub microcode: Option<String>,
+    pub kernel: Option<String>,
+    pub libm: Option<String>,
+    pub tests: Vec<TestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestEntry {
+    pub id: String,
+    pub version: u32,
+    pub fingerprint: String,
+}
+
+/// The readable entries of a history file.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct History {
+    pub entries: Vec<HistoryEntry>,
+    /// Numbers, from 1, of the lines that didn't parse, e.g. a truncated write or an entry of
+    /// another version.
+    pub skipped: Vec<usize>,
+}
+
+/// A value that differs between two history entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// What differs between a run and the one before it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HistoryDiff {
+    /// Ids of the tests whose fingerprint changed.
+    pub changed_tests: Vec<String>,
+    pub microcode: Option<Change>,
+    pub kernel: Option<Change>,
+    pub libm: Option<Change>,
+    pub tool_version: Option<Change>,
+}
+
+impl HistoryEntry {
+    pub fn from_report(report: &Report) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+
+        Self {
+            timestamp,
+            tool_version: report.tool.version.clone(),
+            sample_size: report.sample_size,
+            microcode: microcode_summary(&report.system.microcode),
+            kernel: report.system.kernel.clone(),
+            libm: report.system.libm.clone(),
+            tests: report
+                .tests
+                .iter()
+                .map(|test| TestEntry {
+                    id: test.id.clone(),
+                    version: test.version,
+                    fingerprint: test.stable_fingerprint().to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Compares with `previous`, `None` unless a test present in both (at the same version and
+    /// sample size) changed its fingerprint.
+    pub fn diff(&self, previous: &HistoryEntry) -> Option<HistoryDiff> {
+        if self.sample_size != previous.sample_size {
+            return None;
+        }
+
+        let changed_tests: Vec<String> = self
+            .tests
+            .iter()
+            .filter(|test| {
+                previous.tests.iter().any(|old| {
+                    old.id == test.id
+                        && old.version == test.version
+                        && old.fingerprint != test.fingerprint
+                })
+            })
+            .map(|test| test.id.clone())
+            .collect();
+
+        if changed_tests.is_empty() {
+            return None;
+        }
+
+        let change = |before: &Option<String>, after: &Option<String>| {
+            (before != after).then(|| Change {
+                before: before.clone(),
+                after: after.clone(),
+            })
+        };
+
+        Some(HistoryDiff {
+            changed_tests,
+            microcode: change(&previous.microcode, &self.microcode),
+            kernel: change(&previous.kernel, &self.kernel),
+            libm: change(&previous.libm, &self.libm),
+            tool_version: change(
+                &Some(previous.tool_version.clone()),
+                &Some(self.tool_version.clone()),
+            ),
+        })
+    }
+}
+
+/// Reads the history at `path`, empty if the file doesn't exist yet. Lines that don't parse are
+/// skipped rather than failing the whole history.
+pub fn load(path: &Path) -> Result<History> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(History::default()),
+        Err(err) => return Err(FingerprintError::io(path, err)),
+    };
+
+    let mut history = History::default();
+
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match serde_json::from_str(line) {
+            Ok(entry) => history.entries.push(entry),
+            Err(_) => history.skipped.push(number + 1),
+        }
+    }
+
+    Ok(history)
+}
+
+/// Appends `entry` to the history at `path`, creating it if needed.
+pub fn append(path: &Path, entry: &HistoryEntry) -> Result<()> {
+    let line = serde_json::to_string(entry).expect("a history entry always serializes");
+
+    OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| {
+            // after a truncated write the new entry would continue the broken line
+            let mut last = [b'\n'];
+            if file.seek(SeekFrom::End(-1)).is_ok() {
+                file.read_exact(&mut last)?;
+            }
+            if last[0] != b'\n' {
+                writeln!(file)?;
+            }
+
+            writeln!(file, "{}", line)
+        })
+        .map_err(|err| FingerprintError::io(path, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::MicrocodeRevision;
+    use crate::suite::DenormalTimingTest;
+    use crate::{FingerprintTest, TestReport};
+
+    fn report(fingerprint: &str, microcode: &str, kernel: &str, libm: &str) -> Report {
+        let mut test = TestReport::new(&DenormalTimingTest::default());
+        test.fingerprint = fingerprint.to_string();
+
+        let mut report = Report::new(1, crate::SAMPLE_SIZE);
+        report.system.microcode = vec![MicrocodeRevision {
+            cpu: 0,
+            revision: microcode.to_string(),
+        }];
+        report.system.kernel = Some(kernel.to_string());
+        report.system.libm = Some(libm.to_string());
+        report.tests.push(test);
+        report
+    }
+
+    fn entry(fingerprint: &str, microcode: &str, kernel: &str, libm: &str) -> HistoryEntry {
+        HistoryEntry::from_report(&report(fingerprint, microcode, kernel, libm))
+    }
+
+    fn change(before: &str, after: &str) -> Option<Change> {
+        Some(Change {
+            before: Some(before.to_string()),
+            after: Some(after.to_string()),
+        })
+    }
+
+    #[test]
+    fn entries_summarize_the_report() {
+        let entry = entry("a", "0x2b000603", "6.8.0", "glibc 2.39");
+
+        assert_eq!(entry.microcode.as_deref(), Some("0x2b000603"));
+        assert_eq!(entry.kernel.as_deref(), Some("6.8.0"));
+        assert_eq!(entry.libm.as_deref(), Some("glibc 2.39"));
+        assert_eq!(
+            entry.tests,
+            [TestEntry {
+                id: "denormal-timing".to_string(),
+                version: DenormalTimingTest::default().version(),
+                fingerprint: "a".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn environment_changes_are_reported() {
+        let before = entry("a", "0x1", "6.8.0", "glibc 2.39");
+
+        let diff = entry("b", "0x2", "6.8.0", "glibc 2.39")
+            .diff(&before)
+            .unwrap();
+        assert_eq!(diff.changed_tests, ["denormal-timing"]);
+        assert_eq!(diff.microcode, change("0x1", "0x2"));
+        assert_eq!((diff.kernel, diff.libm), (None, None));
+
+        let diff = entry("b", "0x1", "6.9.1", "glibc 2.39")
+            .diff(&before)
+            .unwrap();
+        assert_eq!(diff.kernel, change("6.8.0", "6.9.1"));
+        assert_eq!((diff.microcode, diff.libm), (None, None));
+
+        let diff = entry("b", "0x1", "6.8.0", "glibc 2.40")
+            .diff(&before)
+            .unwrap();
+        assert_eq!(diff.libm, change("glibc 2.39", "glibc 2.40"));
+        assert_eq!((diff.microcode, diff.kernel), (None, None));
+    }
+
+    #[test]
+    fn unchanged_environments_report_nothing() {
+        let before = entry("a", "0x1", "6.8.0", "glibc 2.39");
+
+        assert_eq!(entry("a", "0x1", "6.8.0", "glibc 2.39").diff(&before), None);
+
+        let diff = entry("b", "0x1", "6.8.0", "glibc 2.39")
+            .diff(&before)
+            .unwrap();
+        assert_eq!(diff.changed_tests, ["denormal-timing"]);
+        assert_eq!(diff.microcode, None);
+        assert_eq!(diff.kernel, None);
+        assert_eq!(diff.libm, None);
+        assert_eq!(diff.tool_version, None);
+    }
+
+    #[test]
+    fn unreadable_lines_are_skipped_and_appending_continues() {
+        let path = std::env::temp_dir().join(format!(
+            "cpu_fingerprint_history_test_{}.jsonl",
+            std::process::id()
+        ));
+        let first = entry("a", "0x1", "6.8.0", "glibc 2.39");
+        let second = entry("b", "0x1", "6.8.0", "glibc 2.39");
+
+        append(&path, &first).unwrap();
+        // an entry of another version, then a write cut short
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"timestamp\": \"never\"}}\n{{\"timestamp\": 1").unwrap();
+        append(&path, &second).unwrap();
+
+        let history = load(&path);
+        fs::remove_file(&path).unwrap();
+        let history = history.unwrap();
+
+        assert_eq!(history.entries, [first, second]);
+        assert_eq!(history.skipped, [2, 3]);
+    }
+}
@@ -0,0 +1,189 @@
+//! Core classes of hybrid CPUs, e.g. performance and efficiency cores, which may compute
+//! differently.
+//!
+//! An unpinned run lands on whichever class the scheduler picks, so on hybrid CPUs every test
+//! is also run pinned to one CPU of each class, and the per-class fingerprints are combined into
+//! a composite fingerprint that doesn't depend on scheduling.
+
+use std::fs;
+
+use serde::{Deserialize, Serialize};
+
+use crate::affinity::{allowed_cpus, run_pinned};
+use crate::cpuid::hybrid_core_type;
+use crate::error::Result;
+use crate::{FingerprintTest, SubFingerprint, combine_fingerprints, fingerprint_test};
+
+const CORE_TYPE_ATOM: u8 = 0x20;
+const CORE_TYPE_CORE: u8 = 0x40;
+
+/// Logical CPUs of one core class.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoreClass {
+    /// `"performance"` and `"efficiency"` from CPUID or sysfs, `"rank <n>"` from sysfs when there
+    /// are more than two capacities, or `"uniform"` when all CPUs are alike.
+    pub name: String,
+    pub cpus: Vec<usize>,
+}
+
+/// Groups the allowed CPUs by core type, from CPUID leaf 0x1A where the CPU reports itself as
+/// hybrid, otherwise from the `cpu_capacity` sysfs files. Empty if the allowed CPUs can't be
+/// read.
+pub fn detect_core_classes() -> Vec<CoreClass> {
+    let Ok(cpus) = allowed_cpus() else {
+        return Vec::new();
+    };
+
+    if hybrid_core_type().is_some() {
+        let types: Option<Vec<(usize, u8)>> = cpus
+            .iter()
+            .map(|&cpu| {
+                let core_type = run_pinned(cpu, hybrid_core_type).ok().flatten()?;
+                Some((cpu, core_type))
+            })
+            .collect();
+
+        if let Some(types) = types {
+            return group(types.into_iter().map(|(cpu, core_type)| {
+                let name = match core_type {
+                    CORE_TYPE_CORE => "performance".to_string(),
+                    CORE_TYPE_ATOM => "efficiency".to_string(),
+                    other => format!("core type {:#x}", other),
+                };
+                (cpu, name)
+            }));
+        }
+    }
+
+    let capacities: Option<Vec<(usize, u32)>> = cpus
+        .iter()
+        .map(|&cpu| Some((cpu, cpu_capacity(cpu)?)))
+        .collect();
+
+    if let Some(classes) = capacities.and_then(|capacities| capacity_classes(&capacities)) {
+        return classes;
+    }
+
+    vec![CoreClass {
+        name: "uniform".to_string(),
+        cpus,
+    }]
+}
+
+/// Classes from `(cpu, capacity)` pairs, `None` if all capacities are equal.
+///
+/// Classes are named by rank rather than by capacity, which the kernel may rescale: with two
+/// distinct capacities `"performance"` and `"efficiency"`, otherwise `"rank 0"` for the highest
+/// capacity, `"rank 1"` for the next and so on.
+pub fn capacity_classes(capacities: &[(usize, u32)]) -> Option<Vec<CoreClass>> {
+    let mut distinct: Vec<u32> = capacities.iter().map(|&(_, capacity)| capacity).collect();
+    distinct.sort_unstable_by(|a, b| b.cmp(a));
+    distinct.dedup();
+
+    if distinct.len() < 2 {
+        return None;
+    }
+
+    Some(group(capacities.iter().map(|&(cpu, capacity)| {
+        let rank = distinct.iter().position(|&c| c == capacity).unwrap();
+        let name = match (distinct.len(), rank) {
+            (2, 0) => "performance".to_string(),
+            (2, _) => "efficiency".to_string(),
+            _ => format!("rank {}", rank),
+        };
+        (cpu, name)
+    })))
+}
+
+fn group(cpus: impl Iterator<Item = (usize, String)>) -> Vec<CoreClass> {
+    let mut classes: Vec<CoreClass> = Vec::new();
+
+    for (cpu, name) in cpus {
+        match classes.iter_mut().find(|class| class.name == name) {
+            Some(class) => class.cpus.push(cpu),
+            None => classes.push(CoreClass {
+                name,
+                cpus: vec![cpu],
+            }),
+        }
+    }
+
+    classes.sort_by(|a, b| a.name.cmp(&b.name));
+    classes
+}
+
+fn cpu_capacity(cpu: usize) -> Option<u32> {
+    let path = format!("/sys/devices/system/cpu/cpu{}/cpu_capacity", cpu);
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Runs `test` pinned to the first CPU of each class and returns the fingerprint of each class
+/// together with their composite.
+pub fn fingerprint_classes(
+    test: &dyn FingerprintTest,
+    classes: &[CoreClass],
+) -> Result<(Vec<SubFingerprint>, String)> {
+    let mut parts = Vec::with_capacity(classes.len());
+
+    for class in classes {
+        let Some(&cpu) = class.cpus.first() else {
+            continue;
+        };
+
+        let results = run_pinned(cpu, || test.run())??;
+        let (fingerprint, _) = fingerprint_test(test, &results);
+
+        parts.push(SubFingerprint {
+            name: class.name.clone(),
+            fingerprint,
+        });
+    }
+
+    // classes are sorted by name, so the composite doesn't depend on detection order
+    let composite = combine_fingerprints(test.id(), &parts);
+
+    Ok((parts, composite))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(classes: &[CoreClass]) -> Vec<(&str, &[usize])> {
+        classes
+            .iter()
+            .map(|class| (class.name.as_str(), class.cpus.as_slice()))
+            .collect()
+    }
+
+    #[test]
+    fn equal_capacities_are_not_classes() {
+        assert_eq!(capacity_classes(&[(0, 1024), (1, 1024)]), None);
+    }
+
+    #[test]
+    fn classes_dont_depend_on_the_capacity_values() {
+        let classes = capacity_classes(&[(0, 1024), (1, 1024), (2, 512), (3, 512)]).unwrap();
+        let rescaled = capacity_classes(&[(0, 1000), (1, 1000), (2, 436), (3, 436)]).unwrap();
+
+        assert_eq!(classes, rescaled);
+        assert_eq!(
+            names(&classes),
+            [("efficiency", &[2, 3][..]), ("performance", &[0, 1][..])]
+        );
+    }
+
+    #[test]
+    fn more_than_two_capacities_are_ranked() {
+        let classes = capacity_classes(&[(0, 160), (1, 1024), (2, 512), (3, 160)]).unwrap();
+
+        assert_eq!(
+            names(&classes),
+            [
+                ("rank 0", &[1][..]),
+                ("rank 1", &[2][..]),
+                ("rank 2", &[0, 3][..])
+            ]
+        );
+    }
+}
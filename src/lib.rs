@@ -0,0 +1,41 @@
+//! Floating point fingerprinting of the silicon (and math library) a program runs on.
+//!
+//! Every test implements [`FingerprintTest`] and produces a vector of `f64` results,
+//! [`calculate_fingerprint_full_precision`] turns that vector into a fingerprint.
+
+pub mod affinity;
+pub mod compare;
+pub mod cpuid;
+pub mod encoding;
+mod error;
+mod fingerprint;
+pub mod folding;
+pub mod history;
+pub mod hybrid;
+pub mod math;
+pub mod microarch;
+pub mod mxcsr;
+pub mod per_core;
+pub mod platform;
+pub mod report;
+mod sha256;
+pub mod softfloat;
+pub mod suite;
+pub mod topology;
+
+pub use compare::{Cause, Comparison, compare_reports};
+pub use error::{FingerprintError, Result};
+pub use fingerprint::{
+    FINGERPRINT_PREFIX, SubFingerprint, calculate_fingerprint_full_precision, combine_fingerprints,
+    fingerprint_test,
+};
+pub use report::{Report, TestReport};
+pub use suite::{
+    EnhancedDenormalTest, FingerprintTest, TranscendentalFunctionTest, find_test, registry,
+};
+
+/// Default number of times each test is run to verify the fingerprint is stable.
+pub const CONSISTENCY_RUNS: usize = 3;
+
+/// Default number of results produced by a test.
+pub const SAMPLE_SIZE: usize = 1230;
@@ -1,16 +1,226 @@
-use core::cmp::min;
-use std::collections::HashMap;
-use std::collections::hash_map::DefaultHasher;
 use std::env::consts;
-use std::f64::consts::PI;
 use std::fs::File;
-use std::hash::{Hash, Hasher};
-use std::io::Write;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+
+use cpu_fingerprint::compare::{Cause, TestStatus, compare_reports};
+use cpu_fingerprint::encoding::write_results;
+use cpu_fingerprint::folding::folded_results;
+use cpu_fingerprint::history::{self, Change, HISTORY_FILE, HistoryEntry};
+use cpu_fingerprint::hybrid::fingerprint_classes;
+use cpu_fingerprint::math::MathLibrary;
+use cpu_fingerprint::mxcsr::{SubnormalMode, rounding_sweep, subnormal_sweep};
+use cpu_fingerprint::per_core::{CoreReport, run_per_core};
+use cpu_fingerprint::platform::microcode_summary;
+use cpu_fingerprint::report::{ReferenceCheck, SystemInfo};
+use cpu_fingerprint::softfloat::Arithmetic;
+use cpu_fingerprint::suite::{ResultKind, opt_in_tests};
+use cpu_fingerprint::{
+    CONSISTENCY_RUNS, FINGERPRINT_PREFIX, FingerprintError, Report, Result, SAMPLE_SIZE,
+    TestReport, find_test, registry,
+};
+
+// Exit codes, 2 is used by clap for usage errors
+const EXIT_DIFFERENT: u8 = 1;
+const EXIT_INCONSISTENT: u8 = 3;
+const EXIT_IO: u8 = 4;
+const EXIT_UNKNOWN_TEST: u8 = 5;
+const EXIT_UNSUPPORTED_CPU: u8 = 6;
+const EXIT_INVALID_CONFIGURATION: u8 = 7;
+const EXIT_INVALID_REPORT: u8 = 8;
+const EXIT_AFFINITY: u8 = 9;
+
+/// High Complexity Silicon Variation Detector
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the fingerprint tests and save a report (the default)
+    Run(RunArgs),
+    /// List the available tests
+    List,
+    /// Compare two JSON reports value by value
+    Compare { left: PathBuf, right: PathBuf },
+    /// Print the system information only
+    Info,
+}
+
+#[derive(clap::Args)]
+struct RunArgs {
+    /// Test id to run, may be repeated (default: all tests)
+    #[arg(short, long = "test", value_name = "ID")]
+    tests: Vec<String>,
+
+    /// Number of times each test is run to check consistency
+    #[arg(short, long, default_value_t = CONSISTENCY_RUNS)]
+    runs: usize,
+
+    /// Inputs per test for denormal, fma, reciprocal and denormal-timing [default: 1230]; the
+    /// other tests have fixed inputs and reject it when selected with --test
+    #[arg(short, long)]
+    sample_size: Option<usize>,
+
+    /// Report path (default: fingerprint_<arch>-<sockets>s<cores>c<threads>t.<txt|json>), raw
+    /// results go next to it as .bin
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Report format
+    #[arg(short, long, value_enum, default_value_t = Format::Text)]
+    format: Format,
+
+    /// Also run every libm based test on the built-in portable math library, so `compare` can
+    /// tell library differences from hardware ones
+    #[arg(short, long)]
+    attribution: bool,
+
+    /// Check every result of the tests that support it against a software IEEE-754
+    /// implementation of the basic operations
+    #[arg(long)]
+    reference: bool,
+
+    /// Also run every test once under each MXCSR rounding mode (x86_64 only)
+    #[arg(long)]
+    rounding_sweep: bool,
+
+    /// Also run every test once under each FTZ/DAZ combination (x86_64 only)
+    #[arg(long)]
+    subnormal_sweep: bool,
+
+    /// File the fingerprints of every run are appended to, to report what changed since the
+    /// previous run
+    #[arg(long, value_name = "PATH", default_value = HISTORY_FILE)]
+    history: PathBuf,
+
+    /// Don't read or update the history file
+    #[arg(long)]
+    no_history: bool,
+
+    /// Also run every test on a thread pinned to each logical CPU in turn and flag CPUs that
+    /// disagree (Linux only)
+    #[arg(long)]
+    per_core: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
+impl Default for RunArgs {
+    fn default() -> Self {
+        Self {
+            tests: Vec::new(),
+            runs: CONSISTENCY_RUNS,
+            sample_size: None,
+            output: None,
+            format: Format::Text,
+            attribution: false,
+            reference: false,
+            rounding_sweep: false,
+            subnormal_sweep: false,
+            history: PathBuf::from(HISTORY_FILE),
+            no_history: false,
+            per_core: false,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let result = match cli.command.unwrap_or(Command::Run(RunArgs::default())) {
+        Command::Run(args) => run(args),
+        Command::List => {
+            list();
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Compare { left, right } => compare(&left, &right),
+        Command::Info => {
+            println!(
+                "{}",
+                system_info(&SystemInfo::current(), SubnormalMode::current())
+            );
+            Ok(ExitCode::SUCCESS)
+        }
+    };
+
+    result.unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        eprintln!("{}", hint(&err));
+        ExitCode::from(exit_code(&err))
+    })
+}
+
+fn exit_code(err: &FingerprintError) -> u8 {
+    match err {
+        FingerprintError::Io { .. } => EXIT_IO,
+        FingerprintError::UnknownTest(_) => EXIT_UNKNOWN_TEST,
+        FingerprintError::UnsupportedCpuFeature { .. } => EXIT_UNSUPPORTED_CPU,
+        FingerprintError::InvalidConfiguration(_) => EXIT_INVALID_CONFIGURATION,
+        FingerprintError::InvalidReport { .. } => EXIT_INVALID_REPORT,
+        FingerprintError::Affinity { .. } => EXIT_AFFINITY,
+    }
+}
 
-const CONSISTENCY_RUNS: usize = 3;
-const SAMPLE_SIZE: usize = 1230;
+fn hint(err: &FingerprintError) -> &'static str {
+    match err {
+        FingerprintError::Io { .. } => "Check that the path exists and has the right permissions",
+        FingerprintError::UnknownTest(_) => {
+            "Run `cpu_fingerprint list` to see the available test ids"
+        }
+        FingerprintError::UnsupportedCpuFeature { .. } => {
+            "Leave this test out with --test, it can't run on this CPU"
+        }
+        FingerprintError::InvalidConfiguration(_) => {
+            "Adjust --runs, --sample-size or --output, see `cpu_fingerprint run --help`"
+        }
+        FingerprintError::InvalidReport { .. } => {
+            "Reports are compared as JSON, create them with `cpu_fingerprint run --format json`"
+        }
+        FingerprintError::Affinity { .. } => {
+            "Check that the CPU is online and allowed for this process (taskset, cgroups)"
+        }
+    }
+}
+
+fn run(args: RunArgs) -> Result<ExitCode> {
+    if args.runs == 0 {
+        return Err(FingerprintError::InvalidConfiguration(
+            "--runs must be at least 1".to_string(),
+        ));
+    }
+
+    let sample_size = args.sample_size.unwrap_or(SAMPLE_SIZE);
+    let tests = if args.tests.is_empty() {
+        registry(sample_size)
+    } else {
+        args.tests
+            .iter()
+            .map(|id| find_test(id, sample_size))
+            .collect::<Result<Vec<_>>>()?
+    };
+
+    for test in tests.iter() {
+        if args.sample_size.is_some() && !args.tests.is_empty() && test.sample_size().is_none() {
+            return Err(FingerprintError::InvalidConfiguration(format!(
+                "the {} test has fixed inputs and takes no --sample-size",
+                test.id()
+            )));
+        }
+
+        test.validate()?;
+    }
 
-fn main() {
     println!("High Complexity Silicon Variation Detector");
     println!("=========================================");
     println!(
@@ -18,208 +228,708 @@ fn main() {
     );
     println!(
         "Each test will be run {} times to verify fingerprint consistency",
-        CONSISTENCY_RUNS
+        args.runs
     );
 
-    let sys_info = format!(
-        "System Information:\n\
-        OS: {}\n\
-        CPU: {}\n\
-        Cores: {}\n",
-        consts::OS,
-        consts::ARCH,
-        num_cpus::get()
-    );
+    let mut report = Report::new(args.runs, sample_size);
+
+    let sys_info = system_info(&report.system, report.subnormal_mode);
     println!("{}", sys_info);
 
-    let filename = format!("fingerprint_{}-{}c.txt", consts::ARCH, num_cpus::get());
+    if let Some(mode) = report.subnormal_mode
+        && mode != SubnormalMode::IEEE
+    {
+        eprintln!(
+            "warning: {} is set, subnormal results won't follow IEEE-754",
+            mode
+        );
+    }
 
-    let mut file = File::create(&filename).expect("Could not create output file");
-    file.write_all(sys_info.as_bytes()).expect("Bruh nah");
+    let folded = folded_results();
+    if !folded.is_empty() {
+        eprintln!(
+            "warning: {} expressions give different results when constant folded, values computed at build time would fingerprint the build host:",
+            folded.len()
+        );
+
+        for result in folded.iter() {
+            eprintln!(
+                "  {}: {:?} folded vs {:?} at runtime",
+                result.expression, result.folded, result.runtime
+            );
+        }
+    }
 
-    let test_names = [
-        "Enhanced Denormal Numbers Test",
-        "Transcendental Function Test",
-    ];
+    let filename = args.output.unwrap_or_else(|| {
+        let extension = match args.format {
+            Format::Text => "txt",
+            Format::Json => "json",
+        };
+        let machine = match &report.system.topology {
+            Some(topology) => format!(
+                "{}s{}c{}t",
+                topology.sockets, topology.cores, topology.threads
+            ),
+            None => format!("{}c", num_cpus::get()),
+        };
+        PathBuf::from(format!(
+            "fingerprint_{}-{}.{}",
+            consts::ARCH,
+            machine,
+            extension
+        ))
+    });
+    let raw_filename = filename.with_extension("bin");
+    if raw_filename == filename {
+        return Err(FingerprintError::InvalidConfiguration(format!(
+            "--output {} would be overwritten by the raw results, pick another extension",
+            filename.display()
+        )));
+    }
 
-    for &name in test_names.iter() {
-        println!("\nRunning: {}", name);
-        file.write_all(format!("\n\n{}\n", name).as_bytes())
-            .expect("Not happening");
+    for test in tests.iter() {
+        println!("\nRunning: {}", test.name());
 
-        let mut fingerprints = HashMap::new();
-        let mut first_run_results = Vec::new();
+        let mut test_report = TestReport::new(test.as_ref());
 
-        for run in 1..=CONSISTENCY_RUNS {
-            println!("Run {}/{}...", run, CONSISTENCY_RUNS);
+        for run in 1..=args.runs {
+            println!("Run {}/{}...", run, args.runs);
 
-            let results = match name {
-                "Enhanced Denormal Numbers Test" => enhanced_denormal_test(),
-                "Transcendental Function Test" => transcendental_function_test(),
-                _ => panic!("Bro..."),
-            };
+            let results = test.run()?;
+            let fingerprint = test_report.add_run(test.as_ref(), &results);
+            println!("→ Fingerprint: {}", fingerprint);
 
             if run == 1 {
-                first_run_results = results.clone();
+                test_report.raw_results = results;
             }
+        }
 
-            let fingerprint = calculate_fingerprint_full_precision(&results);
-
-            *fingerprints.entry(fingerprint.clone()).or_insert(0) += 1;
+        for note in test_report.notes.iter() {
+            println!("Note: {}", note);
+        }
 
-            println!("→ Fingerprint: {}", fingerprint);
+        for entry in test_report.consistency.iter() {
+            println!(
+                "→ Consistency: {}/{} runs ({:.1}%) - {}",
+                entry.count,
+                args.runs,
+                consistency_percentage(entry.count, args.runs),
+                consistency_status(entry.count, args.runs)
+            );
         }
 
-        file.write_all(
-            format!(
-                "Raw results from first run ({} values, showing first 10):\n",
-                first_run_results.len()
-            )
-            .as_bytes(),
-        )
-        .expect("Failed result preview");
+        if args.attribution
+            && let Some(portable) = test.with_math(MathLibrary::Portable)
+        {
+            println!("Attribution: running with portable math...");
 
-        for i in 0..min(10, first_run_results.len()) {
-            file.write_all(format!("{:4}: {:?}\n", i, first_run_results[i]).as_bytes())
-                .unwrap();
+            let portable_report = TestReport::collect(portable.as_ref(), args.runs)?;
+            println!("→ Fingerprint: {}", portable_report.fingerprint);
+
+            if !portable_report.is_consistent() {
+                println!("→ Consistency: INCONSISTENT with portable math");
+            }
+
+            test_report.portable = Some(Box::new(portable_report));
         }
 
-        file.write_all(format!("\nConsistency check over {} runs:\n", CONSISTENCY_RUNS).as_bytes())
-            .expect("no");
+        if args.reference
+            && let Some(reference) = test.with_arithmetic(Arithmetic::SoftFloat)
+        {
+            println!("Reference: running with software floating point...");
 
-        for (fingerprint, count) in fingerprints.iter() {
-            let consistency_percentage = (*count as f64 / CONSISTENCY_RUNS as f64) * 100.0;
+            let check = ReferenceCheck::run(reference.as_ref(), &test_report.raw_results)?;
+            println!(
+                "→ {}/{} results match the IEEE-754 reference",
+                check.exact,
+                test_report.raw_results.len()
+            );
 
-            let consistency_status = if *count == CONSISTENCY_RUNS {
-                "CONSISTENT"
-            } else {
-                "INCONSISTENT"
-            };
+            test_report.reference = Some(check);
+        }
+
+        if args.rounding_sweep && test.follows_mxcsr() {
+            println!("Rounding sweep...");
+
+            test_report.rounding = rounding_sweep(test.as_ref())?;
+            for entry in test_report.rounding.iter() {
+                println!("→ Rounding {}: {}", entry.mode.name(), entry.fingerprint);
+            }
+        }
 
-            file.write_all(
-                format!(
-                    "Fingerprint: {} - occurred {} out of {} times ({:.1}%) - {}\n",
-                    fingerprint,
-                    count,
-                    CONSISTENCY_RUNS,
-                    consistency_percentage,
-                    consistency_status
-                )
-                .as_bytes(),
-            )
-            .unwrap();
+        if args.subnormal_sweep && test.follows_mxcsr() {
+            println!("Subnormal sweep...");
 
+            test_report.subnormal = subnormal_sweep(test.as_ref())?;
+            for entry in test_report.subnormal.iter() {
+                println!("→ {}: {}", entry.mode, entry.fingerprint);
+            }
             println!(
-                "→ Consistency: {}/{} runs ({:.1}%) - {}",
-                count, CONSISTENCY_RUNS, consistency_percentage, consistency_status
+                "→ Default environment matches: {}",
+                subnormal_matches(&test_report)
             );
         }
+
+        if report.system.core_classes.len() > 1 && !test.pins_threads() {
+            println!("Core classes...");
+
+            let (parts, composite) =
+                fingerprint_classes(test.as_ref(), &report.system.core_classes)?;
+            for part in parts.iter() {
+                println!("→ {}: {}", part.name, part.fingerprint);
+            }
+            println!("→ Composite fingerprint: {}", composite);
+
+            test_report.core_classes = parts;
+            test_report.composite_fingerprint = Some(composite);
+        }
+
+        report.tests.push(test_report);
+    }
+
+    if args.per_core {
+        println!("\nRunning every test pinned to each logical CPU...");
+
+        report.per_core = run_per_core(&tests)?;
+        print!("{}", per_core_matrix(&report.per_core));
+    }
+
+    // written only once every test succeeded, so a failed run leaves no partial dump
+    File::create(&raw_filename)
+        .and_then(|mut raw_file| {
+            report.tests.iter().try_for_each(|test_report| {
+                write_results(&mut raw_file, &test_report.id, &test_report.raw_results)
+            })
+        })
+        .map_err(|err| FingerprintError::io(&raw_filename, err))?;
+
+    match args.format {
+        Format::Text => File::create(&filename)
+            .and_then(|mut file| write_text_report(&mut file, &sys_info, &report))
+            .map_err(|err| FingerprintError::io(&filename, err))?,
+        Format::Json => report.save(&filename)?,
     }
 
-    println!("\nTests completed! Results saved to {}", filename);
+    println!(
+        "\nTests completed! Results saved to {} (raw results in {})",
+        filename.display(),
+        raw_filename.display()
+    );
     println!("Run this program on different machines to compare silicon-level differences.");
+
+    if !args.no_history
+        && let Err(err) = update_history(&args.history, &report)
+    {
+        eprintln!("warning: history not updated: {}", err);
+    }
+
+    let consistent = report.tests.iter().all(|test| {
+        test.is_consistent() && test.portable.as_ref().is_none_or(|p| p.is_consistent())
+    }) && report.per_core.iter().all(|core| core.outliers.is_empty());
+
+    if consistent {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(EXIT_INCONSISTENT))
+    }
+}
+
+fn update_history(path: &Path, report: &Report) -> Result<()> {
+    let entry = HistoryEntry::from_report(report);
+
+    // an unreadable history still gets the new entry
+    let previous = match history::load(path) {
+        Ok(mut history) => {
+            if !history.skipped.is_empty() {
+                eprintln!(
+                    "warning: skipped {} unreadable lines of {}",
+                    history.skipped.len(),
+                    path.display()
+                );
+            }
+            history.entries.pop()
+        }
+        Err(err) => {
+            eprintln!("warning: history not read: {}", err);
+            None
+        }
+    };
+
+    if let Some(previous) = previous
+        && let Some(diff) = entry.diff(&previous)
+    {
+        println!(
+            "\nFingerprints changed since the previous run: {}",
+            diff.changed_tests.join(", ")
+        );
+
+        let changes = [
+            ("microcode", &diff.microcode),
+            ("kernel", &diff.kernel),
+            ("libm", &diff.libm),
+            ("tool version", &diff.tool_version),
+        ];
+
+        for (name, change) in changes.iter() {
+            if let Some(change) = change {
+                println!("→ {} changed: {}", name, describe_change(change));
+            }
+        }
+
+        if diff.microcode.is_none() && diff.kernel.is_none() && diff.libm.is_none() {
+            println!("→ microcode, kernel and libm are unchanged");
+        }
+    }
+
+    history::append(path, &entry)
+}
+
+fn describe_change(change: &Change) -> String {
+    format!(
+        "{} → {}",
+        change.before.as_deref().unwrap_or("unknown"),
+        change.after.as_deref().unwrap_or("unknown")
+    )
+}
+
+fn list() {
+    for test in registry(SAMPLE_SIZE) {
+        println!("{:16} {} (v{})", test.id(), test.name(), test.version());
+        println!("{:16} {}", "", test.description());
+    }
+
+    println!("\nOnly run when selected with --test:");
+    for test in opt_in_tests(SAMPLE_SIZE) {
+        println!("{:16} {} (v{})", test.id(), test.name(), test.version());
+        println!("{:16} {}", "", test.description());
+    }
 }
 
-// With lower sample sizes this will not be unique
-fn enhanced_denormal_test() -> Vec<f64> {
-    let mut results = Vec::with_capacity(SAMPLE_SIZE);
+fn system_info(system: &SystemInfo, subnormal_mode: Option<SubnormalMode>) -> String {
+    let subnormals = match subnormal_mode {
+        Some(mode) => mode.to_string(),
+        None => "no MXCSR".to_string(),
+    };
+
+    let mut info = format!(
+        "System Information:\n\
+        OS: {}\n\
+        CPU: {}\n\
+        Cores: {}\n\
+        Subnormals: {}\n\
+        Microcode: {}\n\
+        Kernel: {}\n\
+        Libm: {}\n",
+        system.os,
+        system.arch,
+        system.logical_cpus,
+        subnormals,
+        microcode_summary(&system.microcode).unwrap_or_else(|| "unknown".to_string()),
+        system.kernel.as_deref().unwrap_or("unknown"),
+        system.libm.as_deref().unwrap_or("unknown")
+    );
+
+    if let Some(topology) = &system.topology {
+        info.push_str(&format!(
+            "Topology: {} sockets, {} cores, {} threads ({} per core), {} NUMA nodes\n",
+            topology.sockets,
+            topology.cores,
+            topology.threads,
+            topology.threads_per_core(),
+            topology.numa_nodes.len()
+        ));
+
+        for node in topology.numa_nodes.iter() {
+            info.push_str(&format!(
+                "NUMA node {}: CPUs {}\n",
+                node.id,
+                cpu_list(&node.cpus)
+            ));
+        }
+    }
+
+    if system.core_classes.len() > 1 {
+        for class in system.core_classes.iter() {
+            info.push_str(&format!(
+                "Core class {}: CPUs {}\n",
+                class.name,
+                cpu_list(&class.cpus)
+            ));
+        }
+    }
 
-    let starting_values = [
-        1e-308,
-        2e-308,
-        5e-308,
-        1e-307,
-        1e-320,
-        2.2250738585072014e-308,
-    ];
+    if let Some(cpu) = &system.cpu {
+        let microarchitecture = match &system.microarchitecture {
+            Some(microarchitecture) => microarchitecture.to_string(),
+            None => "unknown".to_string(),
+        };
+
+        info.push_str(&format!(
+            "Vendor: {}\n\
+            Brand: {}\n\
+            Family {} Model {} Stepping {} (signature {:#x})\n\
+            Microarchitecture: {}\n\
+            Hypervisor: {}\n\
+            Features: {}\n",
+            cpu.vendor,
+            cpu.brand,
+            cpu.family,
+            cpu.model,
+            cpu.stepping,
+            cpu.signature,
+            microarchitecture,
+            cpu.hypervisor_vendor.as_deref().unwrap_or("none"),
+            cpu.features.join(" ")
+        ));
+
+        // sysfs also knows how many instances of each cache there are
+        if system.topology.is_none() {
+            for cache in cpu.caches.iter() {
+                info.push_str(&format!(
+                    "L{} {:?} cache: {} KiB, {}-way, {} byte lines, shared by {}\n",
+                    cache.level,
+                    cache.kind,
+                    cache.size / 1024,
+                    cache.ways,
+                    cache.line_size,
+                    cache.shared_by
+                ));
+            }
+        }
+    }
+
+    if let Some(topology) = &system.topology {
+        for cache in topology.caches.iter() {
+            info.push_str(&format!(
+                "L{} {:?} cache: {} KiB, {}-way, {} byte lines, shared by {}, {} instances\n",
+                cache.level,
+                cache.kind,
+                cache.size / 1024,
+                cache.ways,
+                cache.line_size,
+                cache.shared_by,
+                cache.instances
+            ));
+        }
+    }
 
-    for &start in starting_values.iter() {
-        let mut x = start;
-        let mut y = start * 1.112345;
+    info
+}
 
-        for i in 0..SAMPLE_SIZE / starting_values.len() {
-            x = x / 1.1123156 + x * 0.9123545676;
-            y = y * 0.951235467 + y / 1.05123245;
+fn compare(left_path: &Path, right_path: &Path) -> Result<ExitCode> {
+    let left = Report::load(left_path)?;
+    let right = Report::load(right_path)?;
 
-            let combined =
-                x * (1.0 + (i as f64 * 0.01).sin()) + y * (1.0 + (i as f64 * 0.01).cos());
+    println!(
+        "Comparing {} with {}",
+        left_path.display(),
+        right_path.display()
+    );
 
-            let final_val =
-                combined + (combined * 1e300).sin() * 1e-308 + (combined * 1e200).atan() * 1e-308;
+    let microarchitecture = |report: &Report| match &report.system.microarchitecture {
+        Some(microarchitecture) => microarchitecture.to_string(),
+        None => "unknown microarchitecture".to_string(),
+    };
+    println!(
+        "{} vs {}",
+        microarchitecture(&left),
+        microarchitecture(&right)
+    );
 
-            results.push(final_val);
+    let comparison = compare_reports(&left, &right);
+
+    for test in comparison.tests.iter() {
+        match test.status {
+            TestStatus::Match => println!("\n{}: MATCH", test.name),
+            TestStatus::VersionMismatch(l, r) => println!(
+                "\n{}: NOT COMPARABLE (test version {} vs {})",
+                test.name, l, r
+            ),
+            TestStatus::OnlyInLeft => println!("\n{}: only in {}", test.name, left_path.display()),
+            TestStatus::OnlyInRight => {
+                println!("\n{}: only in {}", test.name, right_path.display())
+            }
+            TestStatus::Different => {
+                println!(
+                    "\n{}: DIFFERENT ({} of {} results differ)",
+                    test.name,
+                    test.differences.len(),
+                    test.lengths.0.min(test.lengths.1)
+                );
+
+                if !test.differing_parts.is_empty() {
+                    println!("  differing parts: {}", test.differing_parts.join(", "));
+                }
+
+                if test.lengths.0 != test.lengths.1 {
+                    println!(
+                        "  result count differs: {} vs {}",
+                        test.lengths.0, test.lengths.1
+                    );
+                }
+
+                let from_library = test
+                    .differences
+                    .iter()
+                    .filter(|diff| diff.cause == Some(Cause::Library))
+                    .count();
+                let from_hardware = test
+                    .differences
+                    .iter()
+                    .filter(|diff| diff.cause == Some(Cause::Hardware))
+                    .count();
+
+                if from_library + from_hardware > 0 {
+                    println!(
+                        "  attribution: {} disappear with portable math (libm), {} persist (hardware arithmetic)",
+                        from_library, from_hardware
+                    );
+                }
+
+                for diff in test.differences.iter() {
+                    let (left, right, mut detail) = match diff.kind {
+                        ResultKind::Numeric => (
+                            format!("{:?}", diff.left),
+                            format!("{:?}", diff.right),
+                            match diff.ulps {
+                                Some(ulps) => format!("{} ulp", ulps),
+                                None => "NaN".to_string(),
+                            },
+                        ),
+                        ResultKind::Category => (
+                            diff.left.to_string(),
+                            diff.right.to_string(),
+                            "category".to_string(),
+                        ),
+                        ResultKind::Bits => (
+                            format!("{:#018x}", diff.left.to_bits()),
+                            format!("{:#018x}", diff.right.to_bits()),
+                            "bits".to_string(),
+                        ),
+                    };
+
+                    match diff.cause {
+                        Some(Cause::Library) => detail.push_str(", libm"),
+                        Some(Cause::Hardware) => detail.push_str(", hardware"),
+                        None => {}
+                    }
+
+                    println!(
+                        "  [{:5}] {}: {} vs {} ({})",
+                        diff.index,
+                        diff.input.as_deref().unwrap_or("unknown input"),
+                        left,
+                        right,
+                        detail
+                    );
+                }
+            }
         }
     }
 
-    results
+    if comparison.all_match() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(EXIT_DIFFERENT))
+    }
 }
 
-// This has appeared unique regardless of sample size
-#[inline(never)]
-fn transcendental_function_test() -> Vec<f64> {
-    let mut results = Vec::with_capacity(SAMPLE_SIZE);
-    let mut test_values = Vec::with_capacity(500);
+fn write_text_report(file: &mut File, sys_info: &str, report: &Report) -> io::Result<()> {
+    file.write_all(sys_info.as_bytes())?;
+
+    for test_report in report.tests.iter() {
+        write!(file, "\n\n{}\n", test_report.name)?;
+
+        for note in test_report.notes.iter() {
+            writeln!(file, "Note: {}", note)?;
+        }
+
+        let first_run_results = &test_report.raw_results;
+
+        writeln!(
+            file,
+            "Raw results from first run ({} values, showing first 10):",
+            first_run_results.len()
+        )?;
+
+        for (i, value) in first_run_results.iter().enumerate().take(10) {
+            writeln!(file, "{:4}: {:?}", i, value)?;
+        }
+
+        if let Some(portable) = &test_report.portable {
+            writeln!(
+                file,
+                "\nFingerprint with portable math: {}",
+                portable.fingerprint
+            )?;
+        }
+
+        if let Some(reference) = &test_report.reference {
+            writeln!(
+                file,
+                "\nIEEE-754 reference check: {} of {} results match",
+                reference.exact,
+                first_run_results.len()
+            )?;
+
+            for (i, value) in first_run_results.iter().enumerate() {
+                writeln!(file, "{:4}: {:?} - {}", i, value, reference.conformance(i))?;
+            }
+        }
+
+        if !test_report.rounding.is_empty() {
+            writeln!(file, "\nFingerprint per rounding mode:")?;
+
+            for entry in test_report.rounding.iter() {
+                writeln!(file, "{:16} {}", entry.mode.name(), entry.fingerprint)?;
+            }
+        }
+
+        if !test_report.subnormal.is_empty() {
+            writeln!(file, "\nFingerprint per subnormal mode:")?;
+
+            for entry in test_report.subnormal.iter() {
+                writeln!(file, "{:16} {}", entry.mode.to_string(), entry.fingerprint)?;
+            }
+            writeln!(
+                file,
+                "Default environment matches: {}",
+                subnormal_matches(test_report)
+            )?;
+        }
+
+        if let Some(composite) = &test_report.composite_fingerprint {
+            writeln!(file, "\nFingerprint per core class:")?;
+
+            for part in test_report.core_classes.iter() {
+                writeln!(file, "{:16} {}", part.name, part.fingerprint)?;
+            }
+            writeln!(file, "Composite fingerprint: {}", composite)?;
+        }
+
+        if !test_report.sub_fingerprints.is_empty() {
+            writeln!(file, "\nSub-fingerprints from first run:")?;
 
-    test_values.extend_from_slice(&[
-        0.0,
-        1e-15,
-        PI / 6.0,
-        PI / 4.0,
-        PI / 3.0,
-        PI / 2.0,
-        PI,
-        3.0 * PI / 2.0,
-        2.0 * PI,
-        1.0,
-        -1.0,
-        0.5,
-        -0.534634634512312587,
-        1e-10,
-        -1e-10,
-        1e15,
-        -1e15,
-    ]);
+            for part in test_report.sub_fingerprints.iter() {
+                writeln!(file, "{:32} {}", part.name, part.fingerprint)?;
+            }
+        }
 
-    for i in 0..500 {
-        test_values.push(i as f64 * PI / 17.12344658922222221111154657);
+        writeln!(
+            file,
+            "\nConsistency check over {} runs:",
+            report.consistency_runs
+        )?;
+
+        for entry in test_report.consistency.iter() {
+            writeln!(
+                file,
+                "Fingerprint: {} - occurred {} out of {} times ({:.1}%) - {}",
+                entry.fingerprint,
+                entry.count,
+                report.consistency_runs,
+                consistency_percentage(entry.count, report.consistency_runs),
+                consistency_status(entry.count, report.consistency_runs)
+            )?;
+        }
     }
 
-    for &val in test_values.iter() {
-        let sin_val = val.sin();
-        let cos_val = val.cos();
+    if !report.per_core.is_empty() {
+        write!(file, "\n\n{}", per_core_matrix(&report.per_core))?;
+    }
+
+    Ok(())
+}
 
-        let sin_of_sin = (sin_val * 10.0).sin();
-        let exp_of_cos = cos_val.exp() - 1.0;
+// One row per logical CPU with the start of each test's fingerprint
+fn per_core_matrix(cores: &[CoreReport]) -> String {
+    let Some(first) = cores.first() else {
+        return String::new();
+    };
+
+    let mut matrix = String::from(
+        "Per-core fingerprints (first 8 hex digits, * = disagrees with the majority of CPUs, or there is none):\n",
+    );
 
-        let compound1 = val.sinh() * val.cosh() - 0.5 * (2.0 * val).sinh();
-        let compound2 = (val.abs() + 1.0).log10() + (val.abs() + 2.0).log2();
+    let widths: Vec<usize> = first
+        .fingerprints
+        .iter()
+        .map(|f| f.test.len().max(9))
+        .collect();
 
-        let atan_val = f64::atan(val);
-        let tanh_val = f64::tanh(val);
+    let mut header = format!("{:>6} {:>4} {:>4}", "socket", "core", "cpu");
+    for (entry, width) in first.fingerprints.iter().zip(widths.iter()) {
+        header.push_str(&format!("  {:<width$}", entry.test, width = width));
+    }
+    matrix.push_str(header.trim_end());
+    matrix.push('\n');
+
+    let id = |id: Option<u32>| id.map_or("?".to_string(), |id| id.to_string());
+
+    for core in cores {
+        let mut row = format!(
+            "{:>6} {:>4} {:>4}",
+            id(core.socket),
+            id(core.core),
+            core.cpu
+        );
+
+        for (entry, width) in core.fingerprints.iter().zip(widths.iter()) {
+            let hash = entry
+                .fingerprint
+                .strip_prefix(FINGERPRINT_PREFIX)
+                .unwrap_or(&entry.fingerprint);
+            let flag = if core.outliers.contains(&entry.test) {
+                "*"
+            } else {
+                ""
+            };
+            let cell = format!("{:.8}{}", hash, flag);
+            row.push_str(&format!("  {:<width$}", cell, width = width));
+        }
+        matrix.push_str(row.trim_end());
+        matrix.push('\n');
+    }
 
-        results.push(sin_val);
-        results.push(cos_val);
-        results.push(sin_of_sin);
-        results.push(exp_of_cos);
-        results.push(compound1);
-        results.push(compound2);
-        results.push(atan_val);
-        results.push(tanh_val);
+    let outliers: Vec<String> = cores
+        .iter()
+        .filter(|core| !core.outliers.is_empty())
+        .map(|core| format!("CPU {} ({})", core.cpu, core.outliers.join(", ")))
+        .collect();
 
-        let hypot = f64::hypot(sin_val, cos_val);
-        results.push(hypot - 1.0);
+    if !outliers.is_empty() {
+        matrix.push_str(&format!("Disagreeing CPUs: {}\n", outliers.join("; ")));
     }
 
-    results
+    matrix
 }
 
-fn calculate_fingerprint_full_precision(results: &[f64]) -> String {
-    let mut hasher = DefaultHasher::new();
+fn subnormal_matches(test_report: &TestReport) -> String {
+    let matches = test_report.subnormal_matches();
 
-    for val in results {
-        let bits = val.to_bits();
-        bits.hash(&mut hasher);
+    if matches.is_empty() {
+        "none".to_string()
+    } else {
+        let modes: Vec<String> = matches.iter().map(|mode| mode.to_string()).collect();
+        modes.join(" / ")
     }
+}
 
-    format!("{:016x}", hasher.finish())
+fn cpu_list(cpus: &[usize]) -> String {
+    cpus.iter()
+        .map(|cpu| cpu.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn consistency_percentage(count: usize, runs: usize) -> f64 {
+    (count as f64 / runs as f64) * 100.0
+}
+
+fn consistency_status(count: usize, runs: usize) -> &'static str {
+    if count == runs {
+        "CONSISTENT"
+    } else {
+        "INCONSISTENT"
+    }
 }
@@ -0,0 +1,90 @@
+//! Arc tangent, after fdlibm's `s_atan.c`.
+
+use super::high_word;
+
+const ATAN_HI: [f64; 4] = [
+    4.63647609000806093515e-01, // atan(0.5)
+    7.85398163397448278999e-01, // atan(1.0)
+    9.82793723247329054082e-01, // atan(1.5)
+    1.57079632679489655800e+00, // atan(inf)
+];
+
+const ATAN_LO: [f64; 4] = [
+    2.26987774529616870924e-17,
+    3.06161699786838301793e-17,
+    1.39033110312309984516e-17,
+    6.12323399573676603587e-17,
+];
+
+const AT: [f64; 11] = [
+    3.33333333333329318027e-01,
+    -1.99999999998764832476e-01,
+    1.42857142725034663711e-01,
+    -1.11111104054623557880e-01,
+    9.09088713343650656196e-02,
+    -7.69187620504482999495e-02,
+    6.66107313738753120669e-02,
+    -5.83357013379057348645e-02,
+    4.97687799461593236017e-02,
+    -3.65315727442169155270e-02,
+    1.62858201153657823623e-02,
+];
+
+pub fn atan(x: f64) -> f64 {
+    let negative = x.is_sign_negative();
+    let ix = high_word(x) & 0x7fff_ffff;
+    let mut x = x;
+
+    if ix >= 0x4410_0000 {
+        // |x| >= 2^66
+        if x.is_nan() {
+            return x;
+        }
+        let z = ATAN_HI[3] + ATAN_LO[3];
+        return if negative { -z } else { z };
+    }
+
+    let id = if ix < 0x3fdc_0000 {
+        // |x| < 0.4375
+        if ix < 0x3e40_0000 {
+            // |x| < 2^-27
+            return x;
+        }
+        None
+    } else {
+        x = x.abs();
+        if ix < 0x3ff3_0000 {
+            // |x| < 1.1875
+            if ix < 0x3fe6_0000 {
+                // 7/16 <= |x| < 11/16
+                x = (2.0 * x - 1.0) / (2.0 + x);
+                Some(0)
+            } else {
+                // 11/16 <= |x| < 19/16
+                x = (x - 1.0) / (x + 1.0);
+                Some(1)
+            }
+        } else if ix < 0x4003_8000 {
+            // |x| < 2.4375
+            x = (x - 1.5) / (1.0 + 1.5 * x);
+            Some(2)
+        } else {
+            // 2.4375 <= |x| < 2^66
+            x = -1.0 / x;
+            Some(3)
+        }
+    };
+
+    let z = x * x;
+    let w = z * z;
+    let s1 = z * (AT[0] + w * (AT[2] + w * (AT[4] + w * (AT[6] + w * (AT[8] + w * AT[10])))));
+    let s2 = w * (AT[1] + w * (AT[3] + w * (AT[5] + w * (AT[7] + w * AT[9]))));
+
+    match id {
+        None => x - x * (s1 + s2),
+        Some(id) => {
+            let z = ATAN_HI[id] - ((x * (s1 + s2) - ATAN_LO[id]) - x);
+            if negative { -z } else { z }
+        }
+    }
+}
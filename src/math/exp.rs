@@ -0,0 +1,238 @@
+//! exp, expm1 and the hyperbolic functions built on them, after musl.
+
+use super::{high_word, scalbn};
+
+const LN2_HI: f64 = 6.93147180369123816490e-01; // 0x3fe62e42_fee00000
+const LN2_LO: f64 = 1.90821492927058770002e-10; // 0x3dea39ef_35793c76
+const INV_LN2: f64 = 1.44269504088896338700e+00; // 0x3ff71547_652b82fe
+const O_THRESHOLD: f64 = 7.09782712893383973096e+02; // 0x40862e42_fefa39ef
+
+const P1: f64 = 1.66666666666666019037e-01;
+const P2: f64 = -2.77777777770155933842e-03;
+const P3: f64 = 6.61375632143793436117e-05;
+const P4: f64 = -1.65339022054652515390e-06;
+const P5: f64 = 4.13813679705723846039e-08;
+
+const Q1: f64 = -3.33333333333331316428e-02;
+const Q2: f64 = 1.58730158725481460165e-03;
+const Q3: f64 = -7.93650757867487942473e-05;
+const Q4: f64 = 4.00821782732936239552e-06;
+const Q5: f64 = -2.01099218183624371326e-07;
+
+pub fn exp(x: f64) -> f64 {
+    let mut x = x;
+    let hx = high_word(x);
+    let sign = hx >> 31 != 0;
+    let hx = hx & 0x7fff_ffff;
+
+    if hx >= 0x4086_232b {
+        // |x| >= 708.39
+        if x.is_nan() {
+            return x;
+        }
+        if x > O_THRESHOLD {
+            return x * f64::from_bits(0x7fe0_0000_0000_0000);
+        }
+        if x < -745.13321910194110842 {
+            return 0.0;
+        }
+    }
+
+    let (hi, lo, k);
+    if hx > 0x3fd6_2e42 {
+        // |x| > 0.5 ln2
+        k = if hx >= 0x3ff0_a2b2 {
+            (INV_LN2 * x + if sign { -0.5 } else { 0.5 }) as i32
+        } else if sign {
+            -1
+        } else {
+            1
+        };
+        hi = x - k as f64 * LN2_HI;
+        lo = k as f64 * LN2_LO;
+        x = hi - lo;
+    } else if hx > 0x3e30_0000 {
+        // |x| > 2^-28
+        k = 0;
+        hi = x;
+        lo = 0.0;
+    } else {
+        return 1.0 + x;
+    }
+
+    let xx = x * x;
+    let c = x - xx * (P1 + xx * (P2 + xx * (P3 + xx * (P4 + xx * P5))));
+    let y = 1.0 + (x * c / (2.0 - c) - lo + hi);
+
+    if k == 0 { y } else { scalbn(y, k) }
+}
+
+pub fn expm1(x: f64) -> f64 {
+    let mut x = x;
+    let hx = high_word(x) & 0x7fff_ffff;
+    let sign = x.to_bits() >> 63 != 0;
+
+    if hx >= 0x4043_687a {
+        // |x| >= 56 ln2
+        if x.is_nan() {
+            return x;
+        }
+        if sign {
+            return -1.0;
+        }
+        if x > O_THRESHOLD {
+            return x * f64::from_bits(0x7fe0_0000_0000_0000);
+        }
+    }
+
+    let (k, c);
+    if hx > 0x3fd6_2e42 {
+        // |x| > 0.5 ln2
+        let (hi, lo);
+        if hx < 0x3ff0_a2b2 {
+            // and |x| < 1.5 ln2
+            if sign {
+                hi = x + LN2_HI;
+                lo = -LN2_LO;
+                k = -1;
+            } else {
+                hi = x - LN2_HI;
+                lo = LN2_LO;
+                k = 1;
+            }
+        } else {
+            k = (INV_LN2 * x + if sign { -0.5 } else { 0.5 }) as i32;
+            let t = k as f64;
+            hi = x - t * LN2_HI;
+            lo = t * LN2_LO;
+        }
+        x = hi - lo;
+        c = (hi - x) - lo;
+    } else if hx < 0x3c90_0000 {
+        // |x| < 2^-54
+        return x;
+    } else {
+        k = 0;
+        c = 0.0;
+    }
+
+    let hfx = 0.5 * x;
+    let hxs = x * hfx;
+    let r1 = 1.0 + hxs * (Q1 + hxs * (Q2 + hxs * (Q3 + hxs * (Q4 + hxs * Q5))));
+    let t = 3.0 - r1 * hfx;
+    let mut e = hxs * ((r1 - t) / (6.0 - x * t));
+
+    if k == 0 {
+        return x - (x * e - hxs);
+    }
+
+    e = x * (e - c) - c;
+    e -= hxs;
+
+    if k == -1 {
+        return 0.5 * (x - e) - 0.5;
+    }
+    if k == 1 {
+        if x < -0.25 {
+            return -2.0 * (e - (x + 0.5));
+        }
+        return 1.0 + 2.0 * (x - e);
+    }
+
+    let twopk = f64::from_bits(((0x3ff + k) as u64) << 52);
+    if !(0..=56).contains(&k) {
+        let y = x - e + 1.0;
+        let y = if k == 1024 {
+            y * 2.0 * f64::from_bits(0x7fe0_0000_0000_0000)
+        } else {
+            y * twopk
+        };
+        return y - 1.0;
+    }
+
+    let two_neg_k = f64::from_bits(((0x3ff - k) as u64) << 52);
+    if k < 20 {
+        (x - e + (1.0 - two_neg_k)) * twopk
+    } else {
+        (x - (e + two_neg_k) + 1.0) * twopk
+    }
+}
+
+// exp(x) * sign for x > log(f64::MAX), computed as exp(x - k ln2) * 2^k so it doesn't overflow early
+fn expo2(x: f64, sign: f64) -> f64 {
+    const K: u32 = 2043;
+    let kln2 = f64::from_bits(0x40962066151add8b);
+    let scale = f64::from_bits(((0x3ff + K / 2) as u64) << 52);
+
+    exp(x - kln2) * (sign * scale) * scale
+}
+
+pub fn sinh(x: f64) -> f64 {
+    let h = if x.is_sign_negative() { -0.5 } else { 0.5 };
+    let absx = x.abs();
+    let w = high_word(absx);
+
+    if w < 0x4086_2e42 {
+        // |x| < log(f64::MAX)
+        let t = expm1(absx);
+        if w < 0x3ff0_0000 {
+            if w < 0x3ff0_0000 - (26 << 20) {
+                return x;
+            }
+            return h * (2.0 * t - t * t / (t + 1.0));
+        }
+        return h * (t + t / (t + 1.0));
+    }
+
+    expo2(absx, 2.0 * h)
+}
+
+pub fn cosh(x: f64) -> f64 {
+    let x = x.abs();
+    let w = high_word(x);
+
+    if w < 0x3fe6_2e42 {
+        // |x| < log(2)
+        if w < 0x3ff0_0000 - (26 << 20) {
+            return 1.0;
+        }
+        let t = expm1(x);
+        return 1.0 + t * t / (2.0 * (1.0 + t));
+    }
+
+    if w < 0x4086_2e42 {
+        // |x| < log(f64::MAX)
+        let t = exp(x);
+        return 0.5 * (t + 1.0 / t);
+    }
+
+    expo2(x, 1.0)
+}
+
+pub fn tanh(x: f64) -> f64 {
+    let negative = x.is_sign_negative();
+    let x = x.abs();
+    let w = high_word(x);
+
+    let t = if w > 0x3fe1_93ea {
+        // |x| > log(3) / 2 or NaN
+        if w > 0x4034_0000 {
+            // |x| > 20 or NaN
+            1.0 - 0.0 / x
+        } else {
+            1.0 - 2.0 / (expm1(2.0 * x) + 2.0)
+        }
+    } else if w > 0x3fd0_58ae {
+        // |x| > log(5/3) / 2
+        let t = expm1(2.0 * x);
+        t / (t + 2.0)
+    } else if w >= 0x0010_0000 {
+        let t = expm1(-2.0 * x);
+        -t / (t + 2.0)
+    } else {
+        // subnormal
+        x
+    };
+
+    if negative { -t } else { t }
+}
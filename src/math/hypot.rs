@@ -0,0 +1,55 @@
+//! sqrt(x^2 + y^2) without undue overflow, after musl's `hypot`.
+
+// x^2 as hi + lo using Veltkamp splitting
+fn square(x: f64) -> (f64, f64) {
+    const SPLIT: f64 = 134217729.0; // 2^27 + 1
+
+    let xc = x * SPLIT;
+    let xh = x - xc + xc;
+    let xl = x - xh;
+    let hi = x * x;
+    let lo = xh * xh - hi + 2.0 * xh * xl + xl * xl;
+    (hi, lo)
+}
+
+pub fn hypot(x: f64, y: f64) -> f64 {
+    let mut ux = x.to_bits() & (u64::MAX >> 1);
+    let mut uy = y.to_bits() & (u64::MAX >> 1);
+
+    // arrange |x| >= |y|
+    if ux < uy {
+        std::mem::swap(&mut ux, &mut uy);
+    }
+
+    let ex = (ux >> 52) as i32;
+    let ey = (uy >> 52) as i32;
+    let mut x = f64::from_bits(ux);
+    let mut y = f64::from_bits(uy);
+
+    // hypot(inf, nan) == inf
+    if ey == 0x7ff {
+        return y;
+    }
+    if ex == 0x7ff || uy == 0 {
+        return x;
+    }
+    if ex - ey > 64 {
+        return x + y;
+    }
+
+    // scale so squaring neither overflows nor underflows
+    let mut z = 1.0;
+    if ex > 0x3ff + 510 {
+        z = f64::from_bits(0x6bb0_0000_0000_0000); // 2^700
+        x *= f64::from_bits(0x1430_0000_0000_0000);
+        y *= f64::from_bits(0x1430_0000_0000_0000);
+    } else if ey < 0x3ff - 450 {
+        z = f64::from_bits(0x1430_0000_0000_0000); // 2^-700
+        x *= f64::from_bits(0x6bb0_0000_0000_0000);
+        y *= f64::from_bits(0x6bb0_0000_0000_0000);
+    }
+
+    let (hx, lx) = square(x);
+    let (hy, ly) = square(y);
+    z * (ly + lx + hy + hx).sqrt()
+}
@@ -0,0 +1,127 @@
+//! Natural, base 10 and base 2 logarithms, after musl's `log`, `log10` and `log2`.
+
+const LN2_HI: f64 = 6.93147180369123816490e-01; // 0x3fe62e42_fee00000
+const LN2_LO: f64 = 1.90821492927058770002e-10; // 0x3dea39ef_35793c76
+const IVLN10_HI: f64 = 4.34294481878168880939e-01; // 0x3fdbcb7b_15200000
+const IVLN10_LO: f64 = 2.50829467116452752298e-11; // 0x3dbb9438_ca9aadd5
+const LOG10_2_HI: f64 = 3.01029995663611771306e-01; // 0x3fd34413_509f6000
+const LOG10_2_LO: f64 = 3.69423907715893078616e-13; // 0x3d59fef3_11f12b36
+const IVLN2_HI: f64 = 1.44269504072144627571e+00; // 0x3ff71547_65200000
+const IVLN2_LO: f64 = 1.67517131648865118353e-10; // 0x3de705fc_2eefa200
+
+const LG1: f64 = 6.666666666666735130e-01;
+const LG2: f64 = 3.999999999940941908e-01;
+const LG3: f64 = 2.857142874366239149e-01;
+const LG4: f64 = 2.222219843214978396e-01;
+const LG5: f64 = 1.818357216161805012e-01;
+const LG6: f64 = 1.531383769920937332e-01;
+const LG7: f64 = 1.479819860511658591e-01;
+
+// x = 2^k * (1 + f) with sqrt(2)/2 < 1 + f < sqrt(2)
+struct Reduced {
+    k: i32,
+    f: f64,
+    hfsq: f64,
+    // s * (hfsq + R) where s = f / (2 + f) and R the minimax polynomial
+    tail: f64,
+}
+
+// Returns Err with the result for zero, negative, infinite and NaN input
+fn reduce(x: f64) -> Result<Reduced, f64> {
+    let mut x = x;
+    let mut hx = (x.to_bits() >> 32) as u32;
+    let mut k = 0;
+
+    if hx < 0x0010_0000 || hx >> 31 != 0 {
+        if x.to_bits() << 1 == 0 {
+            return Err(-1.0 / (x * x));
+        }
+        if hx >> 31 != 0 {
+            return Err(f64::NAN);
+        }
+        // subnormal, scale x up
+        k -= 54;
+        x *= f64::from_bits(0x4350_0000_0000_0000);
+        hx = (x.to_bits() >> 32) as u32;
+    } else if hx >= 0x7ff0_0000 {
+        return Err(x);
+    } else if hx == 0x3ff0_0000 && x.to_bits() << 32 == 0 {
+        return Err(0.0);
+    }
+
+    hx += 0x3ff0_0000 - 0x3fe6_a09e;
+    k += (hx >> 20) as i32 - 0x3ff;
+    hx = (hx & 0x000f_ffff) + 0x3fe6_a09e;
+    x = f64::from_bits(((hx as u64) << 32) | (x.to_bits() & 0xffff_ffff));
+
+    let f = x - 1.0;
+    let hfsq = 0.5 * f * f;
+    let s = f / (2.0 + f);
+    let z = s * s;
+    let w = z * z;
+    let t1 = w * (LG2 + w * (LG4 + w * LG6));
+    let t2 = z * (LG1 + w * (LG3 + w * (LG5 + w * LG7)));
+
+    Ok(Reduced {
+        k,
+        f,
+        hfsq,
+        tail: s * (hfsq + t2 + t1),
+    })
+}
+
+// log(1 + f) as hi + lo, hi with the low 32 bits cleared so products with it are exact
+fn split_log1p(r: &Reduced) -> (f64, f64) {
+    let hi = f64::from_bits((r.f - r.hfsq).to_bits() & 0xffff_ffff_0000_0000);
+    let lo = r.f - hi - r.hfsq + r.tail;
+    (hi, lo)
+}
+
+pub fn log(x: f64) -> f64 {
+    let r = match reduce(x) {
+        Ok(r) => r,
+        Err(special) => return special,
+    };
+
+    let dk = r.k as f64;
+    r.tail + dk * LN2_LO - r.hfsq + r.f + dk * LN2_HI
+}
+
+pub fn log10(x: f64) -> f64 {
+    let r = match reduce(x) {
+        Ok(r) => r,
+        Err(special) => return special,
+    };
+
+    let (hi, lo) = split_log1p(&r);
+    let dk = r.k as f64;
+
+    let mut val_hi = hi * IVLN10_HI;
+    let y = dk * LOG10_2_HI;
+    let mut val_lo = dk * LOG10_2_LO + (lo + hi) * IVLN10_LO + lo * IVLN10_HI;
+
+    let w = y + val_hi;
+    val_lo += (y - w) + val_hi;
+    val_hi = w;
+
+    val_lo + val_hi
+}
+
+pub fn log2(x: f64) -> f64 {
+    let r = match reduce(x) {
+        Ok(r) => r,
+        Err(special) => return special,
+    };
+
+    let (hi, lo) = split_log1p(&r);
+
+    let mut val_hi = hi * IVLN2_HI;
+    let mut val_lo = (lo + hi) * IVLN2_LO + lo * IVLN2_HI;
+
+    let y = r.k as f64;
+    let w = y + val_hi;
+    val_lo += (y - w) + val_hi;
+    val_hi = w;
+
+    val_lo + val_hi
+}
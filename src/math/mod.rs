@@ -0,0 +1,167 @@
+//! The math functions the tests call, either from the system libm or from a portable
+//! implementation built into the crate.
+//!
+//! The portable functions follow fdlibm/musl and only use `+ - * /` and `sqrt`, which IEEE-754
+//! requires to be correctly rounded. Their results are therefore the same on every conforming
+//! CPU, so a difference that persists with them comes from the hardware, not the library.
+
+// Constants are written exactly as printed in fdlibm so they can be checked against it
+#![allow(clippy::excessive_precision, clippy::approx_constant)]
+
+mod atan;
+mod exp;
+mod hypot;
+mod log;
+mod trig;
+
+use serde::{Deserialize, Serialize};
+
+pub use atan::atan;
+pub use exp::{cosh, exp, expm1, sinh, tanh};
+pub use hypot::hypot;
+pub use log::{log, log2, log10};
+pub use trig::{cos, sin};
+
+/// The libm entry points used by the tests.
+pub trait Math {
+    fn sin(x: f64) -> f64;
+    fn cos(x: f64) -> f64;
+    fn atan(x: f64) -> f64;
+    fn exp(x: f64) -> f64;
+    fn sinh(x: f64) -> f64;
+    fn cosh(x: f64) -> f64;
+    fn tanh(x: f64) -> f64;
+    fn log10(x: f64) -> f64;
+    fn log2(x: f64) -> f64;
+    fn hypot(x: f64, y: f64) -> f64;
+}
+
+/// The math library the program is linked against (glibc, musl, the MSVC CRT, ...).
+pub struct SystemMath;
+
+/// The deterministic implementation in this module.
+pub struct PortableMath;
+
+/// Which [`Math`] implementation a test runs with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MathLibrary {
+    #[default]
+    System,
+    Portable,
+}
+
+impl Math for SystemMath {
+    fn sin(x: f64) -> f64 {
+        x.sin()
+    }
+
+    fn cos(x: f64) -> f64 {
+        x.cos()
+    }
+
+    fn atan(x: f64) -> f64 {
+        x.atan()
+    }
+
+    fn exp(x: f64) -> f64 {
+        x.exp()
+    }
+
+    fn sinh(x: f64) -> f64 {
+        x.sinh()
+    }
+
+    fn cosh(x: f64) -> f64 {
+        x.cosh()
+    }
+
+    fn tanh(x: f64) -> f64 {
+        x.tanh()
+    }
+
+    fn log10(x: f64) -> f64 {
+        x.log10()
+    }
+
+    fn log2(x: f64) -> f64 {
+        x.log2()
+    }
+
+    fn hypot(x: f64, y: f64) -> f64 {
+        x.hypot(y)
+    }
+}
+
+impl Math for PortableMath {
+    fn sin(x: f64) -> f64 {
+        sin(x)
+    }
+
+    fn cos(x: f64) -> f64 {
+        cos(x)
+    }
+
+    fn atan(x: f64) -> f64 {
+        atan(x)
+    }
+
+    fn exp(x: f64) -> f64 {
+        exp(x)
+    }
+
+    fn sinh(x: f64) -> f64 {
+        sinh(x)
+    }
+
+    fn cosh(x: f64) -> f64 {
+        cosh(x)
+    }
+
+    fn tanh(x: f64) -> f64 {
+        tanh(x)
+    }
+
+    fn log10(x: f64) -> f64 {
+        log10(x)
+    }
+
+    fn log2(x: f64) -> f64 {
+        log2(x)
+    }
+
+    fn hypot(x: f64, y: f64) -> f64 {
+        hypot(x, y)
+    }
+}
+
+// Upper 32 bits of the representation, what fdlibm calls the high word
+fn high_word(x: f64) -> u32 {
+    (x.to_bits() >> 32) as u32
+}
+
+// x * 2^n without intermediate overflow or double rounding
+fn scalbn(x: f64, mut n: i32) -> f64 {
+    let mut y = x;
+
+    if n > 1023 {
+        y *= f64::from_bits(0x7fe0_0000_0000_0000);
+        n -= 1023;
+        if n > 1023 {
+            y *= f64::from_bits(0x7fe0_0000_0000_0000);
+            n -= 1023;
+            n = n.min(1023);
+        }
+    } else if n < -1022 {
+        // 2^-1022 * 2^53, keeps the final n below -53 to avoid double rounding in the subnormal range
+        y *= f64::from_bits(0x0360_0000_0000_0000);
+        n += 1022 - 53;
+        if n < -1022 {
+            y *= f64::from_bits(0x0360_0000_0000_0000);
+            n += 1022 - 53;
+            n = n.max(-1022);
+        }
+    }
+
+    y * f64::from_bits(((0x3ff + n) as u64) << 52)
+}
@@ -0,0 +1,305 @@
+//! sin and cos, after musl's `sin`, `cos`, `__sin`, `__cos` and `__rem_pio2`.
+//!
+//! Arguments beyond 2^20 * pi/2 are reduced with an exact multiplication by the binary
+//! expansion of 2/pi (Payne-Hanek) instead of fdlibm's `__rem_pio2_large`.
+
+use super::high_word;
+
+const S1: f64 = -1.66666666666666324348e-01;
+const S2: f64 = 8.33333333332248946124e-03;
+const S3: f64 = -1.98412698298579493134e-04;
+const S4: f64 = 2.75573137070700676789e-06;
+const S5: f64 = -2.50507602534068634195e-08;
+const S6: f64 = 1.58969099521155010221e-10;
+
+const C1: f64 = 4.16666666666666019037e-02;
+const C2: f64 = -1.38888888888741095749e-03;
+const C3: f64 = 2.48015872894767294178e-05;
+const C4: f64 = -2.75573143513906633035e-07;
+const C5: f64 = 2.08757232129817482790e-09;
+const C6: f64 = -1.13596475577881948265e-11;
+
+const TO_INT: f64 = 1.5 / f64::EPSILON;
+const PIO4: f64 = std::f64::consts::FRAC_PI_4;
+const INV_PIO2: f64 = 6.36619772367581382433e-01; // 0x3fe45f30_6dc9c883
+const PIO2_1: f64 = 1.57079632673412561417e+00; // first 33 bits of pi/2
+const PIO2_1T: f64 = 6.07710050650619224932e-11; // pi/2 - PIO2_1
+const PIO2_2: f64 = 6.07710050630396597660e-11; // second 33 bits of pi/2
+const PIO2_2T: f64 = 2.02226624879595063154e-21; // pi/2 - (PIO2_1 + PIO2_2)
+const PIO2_3: f64 = 2.02226624871116645580e-21; // third 33 bits of pi/2
+const PIO2_3T: f64 = 8.47842766036889956997e-32; // pi/2 - (PIO2_1 + PIO2_2 + PIO2_3)
+const PIO2_HI: f64 = std::f64::consts::FRAC_PI_2;
+const PIO2_LO: f64 = 6.123233995736766e-17; // pi/2 - PIO2_HI
+
+// The first 1280 bits of 2/pi, enough for the largest finite f64
+const TWO_OVER_PI: [u64; 20] = [
+    0xa2f9836e4e441529,
+    0xfc2757d1f534ddc0,
+    0xdb6295993c439041,
+    0xfe5163abdebbc561,
+    0xb7246e3a424dd2e0,
+    0x06492eea09d1921c,
+    0xfe1deb1cb129a73e,
+    0xe88235f52ebb4484,
+    0xe99c7026b45f7e41,
+    0x3991d639835339f4,
+    0x9c845f8bbdf9283b,
+    0x1ff897ffde05980f,
+    0xef2f118b5a0a6d1f,
+    0x6d367ecf27cb09b7,
+    0x4f463f669e5fea2d,
+    0x7527bac7ebe5f17b,
+    0x3d0739f78a5292ea,
+    0x6bfb5fb11f8d5d08,
+    0x56033046fc7b6bab,
+    0xf0cfbc209af4361d,
+];
+
+pub fn sin(x: f64) -> f64 {
+    let ix = high_word(x) & 0x7fff_ffff;
+
+    if ix <= 0x3fe9_21fb {
+        // |x| ~< pi/4
+        if ix < 0x3e50_0000 {
+            // |x| < 2^-26
+            return x;
+        }
+        return kernel_sin(x, 0.0, false);
+    }
+
+    if ix >= 0x7ff0_0000 {
+        // NaN stays NaN, inf gives the same NaN on every CPU instead of the hardware default
+        return if x.is_nan() { x } else { f64::NAN };
+    }
+
+    let (n, y0, y1) = rem_pio2(x);
+    match n & 3 {
+        0 => kernel_sin(y0, y1, true),
+        1 => kernel_cos(y0, y1),
+        2 => -kernel_sin(y0, y1, true),
+        _ => -kernel_cos(y0, y1),
+    }
+}
+
+pub fn cos(x: f64) -> f64 {
+    let ix = high_word(x) & 0x7fff_ffff;
+
+    if ix <= 0x3fe9_21fb {
+        // |x| ~< pi/4
+        if ix < 0x3e46_a09e {
+            // |x| < 2^-27 * sqrt(2)
+            return 1.0;
+        }
+        return kernel_cos(x, 0.0);
+    }
+
+    if ix >= 0x7ff0_0000 {
+        // NaN stays NaN, inf gives the same NaN on every CPU instead of the hardware default
+        return if x.is_nan() { x } else { f64::NAN };
+    }
+
+    let (n, y0, y1) = rem_pio2(x);
+    match n & 3 {
+        0 => kernel_cos(y0, y1),
+        1 => -kernel_sin(y0, y1, true),
+        2 => -kernel_cos(y0, y1),
+        _ => kernel_sin(y0, y1, true),
+    }
+}
+
+// sin(x + y) for |x| ~< pi/4, y the tail of x
+fn kernel_sin(x: f64, y: f64, has_tail: bool) -> f64 {
+    let z = x * x;
+    let w = z * z;
+    let r = S2 + z * (S3 + z * S4) + z * w * (S5 + z * S6);
+    let v = z * x;
+
+    if has_tail {
+        x - ((z * (0.5 * y - v * r) - y) - v * S1)
+    } else {
+        x + v * (S1 + z * r)
+    }
+}
+
+// cos(x + y) for |x| ~< pi/4, y the tail of x
+fn kernel_cos(x: f64, y: f64) -> f64 {
+    let z = x * x;
+    let w = z * z;
+    let r = z * (C1 + z * (C2 + z * C3)) + w * w * (C4 + z * (C5 + z * C6));
+    let hz = 0.5 * z;
+    let w = 1.0 - hz;
+
+    w + (((1.0 - w) - hz) + (z * r - x * y))
+}
+
+// Returns n and y0 + y1 = x - n * pi/2 with |y0 + y1| ~<= pi/4
+fn rem_pio2(x: f64) -> (i32, f64, f64) {
+    let ix = high_word(x) & 0x7fff_ffff;
+
+    if ix >= 0x4139_21fb {
+        // |x| ~>= 2^20 * pi/2
+        return rem_pio2_large(x);
+    }
+
+    let ex = (ix >> 20) as i32;
+    let exponent = |v: f64| ((v.to_bits() >> 52) & 0x7ff) as i32;
+
+    // rint(x / (pi/2))
+    let mut fn_ = x * INV_PIO2 + TO_INT - TO_INT;
+    let mut n = fn_ as i32;
+    let mut r = x - fn_ * PIO2_1;
+    let mut w = fn_ * PIO2_1T; // good to 85 bits
+
+    if r - w < -PIO4 {
+        n -= 1;
+        fn_ -= 1.0;
+        r = x - fn_ * PIO2_1;
+        w = fn_ * PIO2_1T;
+    } else if r - w > PIO4 {
+        n += 1;
+        fn_ += 1.0;
+        r = x - fn_ * PIO2_1;
+        w = fn_ * PIO2_1T;
+    }
+
+    let mut y0 = r - w;
+    if ex - exponent(y0) > 16 {
+        // second round, good to 118 bits
+        let t = r;
+        w = fn_ * PIO2_2;
+        r = t - w;
+        w = fn_ * PIO2_2T - ((t - r) - w);
+        y0 = r - w;
+
+        if ex - exponent(y0) > 49 {
+            // third round, good to 151 bits
+            let t = r;
+            w = fn_ * PIO2_3;
+            r = t - w;
+            w = fn_ * PIO2_3T - ((t - r) - w);
+            y0 = r - w;
+        }
+    }
+
+    (n, y0, (r - y0) - w)
+}
+
+// Bits pos + 1 ..= pos + 64 of 2/pi, counting the first bit after the binary point as 1
+fn two_over_pi_bits(pos: i32) -> u64 {
+    if pos <= -64 {
+        return 0;
+    }
+    if pos < 0 {
+        return TWO_OVER_PI[0] >> -pos;
+    }
+
+    let word = (pos / 64) as usize;
+    let shift = pos % 64;
+    let hi = TWO_OVER_PI.get(word).copied().unwrap_or(0);
+    let lo = TWO_OVER_PI.get(word + 1).copied().unwrap_or(0);
+
+    if shift == 0 {
+        hi
+    } else {
+        (hi << shift) | (lo >> (64 - shift))
+    }
+}
+
+fn rem_pio2_large(x: f64) -> (i32, f64, f64) {
+    let bits = x.to_bits();
+    let m = (bits & ((1 << 52) - 1)) | (1 << 52);
+    let e = ((bits >> 52) & 0x7ff) as i32 - 1075; // |x| = m * 2^e
+
+    // x * 2/pi mod 4: bits of 2/pi before pos only contribute multiples of 4, the 192 bits
+    // after it leave a product of m * window * 2^-190
+    let pos = e - 2;
+    let window = [
+        two_over_pi_bits(pos),
+        two_over_pi_bits(pos + 64),
+        two_over_pi_bits(pos + 128),
+    ];
+
+    let m = m as u128;
+    let p2 = m * window[2] as u128;
+    let p1 = m * window[1] as u128 + (p2 >> 64);
+    let p0 = m * window[0] as u128 + (p1 >> 64);
+
+    // integer part mod 4 in the top two bits of `high`, the 190 bit fraction below
+    let high = p0 as u64;
+    let mut n = (high >> 62) as i32;
+    let mut frac_high = high & ((1 << 62) - 1);
+    let mut frac_low = ((p1 as u64 as u128) << 64) | (p2 as u64 as u128);
+
+    // round to the nearest quadrant, leaving a fraction in [-1/2, 1/2)
+    let negative_frac = frac_high >> 61 != 0;
+    if negative_frac {
+        n += 1;
+        let borrow = (frac_low != 0) as u64;
+        frac_low = (!frac_low).wrapping_add(1);
+        frac_high = (1 << 62) - frac_high - borrow;
+    }
+
+    // the fraction shifted to 192 bits, split into four exact 48 bit pieces
+    const MASK_48: u128 = (1 << 48) - 1;
+    let top = (frac_high << 2) | (frac_low >> 126) as u64;
+    let low = frac_low << 2;
+    let pieces = [
+        (top >> 16) as f64 * pow2(-48),
+        ((((top & 0xffff) as u128) << 32) | (low >> 96)) as f64 * pow2(-96),
+        ((low >> 48) & MASK_48) as f64 * pow2(-144),
+        (low & MASK_48) as f64 * pow2(-192),
+    ];
+
+    let (mut hi, mut lo) = (0.0, 0.0);
+    for piece in pieces {
+        let (s, err) = two_sum(hi, piece);
+        hi = s;
+        lo += err;
+    }
+    let (hi, lo) = fast_two_sum(hi, lo);
+
+    // (hi + lo) * pi/2
+    let p = hi * PIO2_HI;
+    let tail = two_product_error(hi, PIO2_HI, p) + (hi * PIO2_LO + lo * PIO2_HI);
+    let mut y0 = p + tail;
+    let mut y1 = (p - y0) + tail;
+
+    if negative_frac {
+        y0 = -y0;
+        y1 = -y1;
+    }
+
+    if x.is_sign_negative() {
+        (-n, -y0, -y1)
+    } else {
+        (n, y0, y1)
+    }
+}
+
+fn pow2(exp: i32) -> f64 {
+    f64::from_bits(((0x3ff + exp) as u64) << 52)
+}
+
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    let bb = s - a;
+    (s, (a - (s - bb)) + (b - bb))
+}
+
+fn fast_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let s = a + b;
+    (s, b - (s - a))
+}
+
+// The rounding error of a * b = p, by Dekker's splitting
+fn two_product_error(a: f64, b: f64, p: f64) -> f64 {
+    let split = |v: f64| {
+        let c = 134217729.0 * v; // 2^27 + 1
+        let hi = c - (c - v);
+        (hi, v - hi)
+    };
+
+    let (ah, al) = split(a);
+    let (bh, bl) = split(b);
+    ((ah * bh - p) + ah * bl + al * bh) + al * bl
+}
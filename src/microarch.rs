@@ -0,0 +1,260 @@
+//! Microarchitecture names for CPUID vendor/family/model/stepping signatures.
+
+use std::fmt;
+use std::ops::RangeInclusive;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cpuid::CpuIdentity;
+
+/// The core design a CPU is built on and the product line it was sold as.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Microarchitecture {
+    /// The core microarchitecture, e.g. `"Zen 4"` or `"Skylake"`. CPUs of one generation are
+    /// expected to compute identically.
+    pub generation: String,
+    /// The product codename, e.g. `"Raphael"` or `"Skylake-SP"`.
+    pub codename: String,
+}
+
+impl fmt::Display for Microarchitecture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.generation, self.codename)
+    }
+}
+
+const INTEL: &str = "GenuineIntel";
+const AMD: &str = "AuthenticAMD";
+
+const ANY_STEPPING: RangeInclusive<u32> = 0..=0xf;
+
+/// One row of [`TABLE`].
+pub struct Entry {
+    pub vendor: &'static str,
+    pub family: u32,
+    pub models: RangeInclusive<u32>,
+    pub steppings: RangeInclusive<u32>,
+    pub generation: &'static str,
+    pub codename: &'static str,
+}
+
+const fn entry(
+    vendor: &'static str,
+    family: u32,
+    models: RangeInclusive<u32>,
+    generation: &'static str,
+    codename: &'static str,
+) -> Entry {
+    Entry {
+        vendor,
+        family,
+        models,
+        steppings: ANY_STEPPING,
+        generation,
+        codename,
+    }
+}
+
+impl Entry {
+    const fn steppings(self, steppings: RangeInclusive<u32>) -> Self {
+        Self { steppings, ..self }
+    }
+
+    fn matches(&self, vendor: &str, family: u32, model: u32, stepping: u32) -> bool {
+        self.vendor == vendor
+            && self.family == family
+            && self.models.contains(&model)
+            && self.steppings.contains(&stepping)
+    }
+}
+
+/// Known signatures, family and model with the extended fields added in as CPUID reports them.
+/// Rows don't overlap, a model split by stepping has one row per stepping range.
+pub const TABLE: &[Entry] = &[
+    // Intel family 6 big cores
+    entry(INTEL, 6, 0x1a..=0x1a, "Nehalem", "Bloomfield"),
+    entry(INTEL, 6, 0x1e..=0x1f, "Nehalem", "Lynnfield"),
+    entry(INTEL, 6, 0x2e..=0x2e, "Nehalem", "Nehalem-EX"),
+    entry(INTEL, 6, 0x25..=0x25, "Westmere", "Arrandale/Clarkdale"),
+    entry(INTEL, 6, 0x2c..=0x2c, "Westmere", "Gulftown/Westmere-EP"),
+    entry(INTEL, 6, 0x2f..=0x2f, "Westmere", "Westmere-EX"),
+    entry(INTEL, 6, 0x2a..=0x2a, "Sandy Bridge", "Sandy Bridge"),
+    entry(INTEL, 6, 0x2d..=0x2d, "Sandy Bridge", "Sandy Bridge-E/EP"),
+    entry(INTEL, 6, 0x3a..=0x3a, "Ivy Bridge", "Ivy Bridge"),
+    entry(INTEL, 6, 0x3e..=0x3e, "Ivy Bridge", "Ivy Bridge-E/EP"),
+    entry(INTEL, 6, 0x3c..=0x3c, "Haswell", "Haswell"),
+    entry(INTEL, 6, 0x45..=0x46, "Haswell", "Haswell"),
+    entry(INTEL, 6, 0x3f..=0x3f, "Haswell", "Haswell-E/EP"),
+    entry(INTEL, 6, 0x3d..=0x3d, "Broadwell", "Broadwell"),
+    entry(INTEL, 6, 0x47..=0x47, "Broadwell", "Broadwell"),
+    entry(INTEL, 6, 0x4f..=0x4f, "Broadwell", "Broadwell-E/EP"),
+    entry(INTEL, 6, 0x56..=0x56, "Broadwell", "Broadwell-DE"),
+    entry(INTEL, 6, 0x4e..=0x4e, "Skylake", "Skylake"),
+    entry(INTEL, 6, 0x5e..=0x5e, "Skylake", "Skylake"),
+    entry(INTEL, 6, 0x55..=0x55, "Skylake", "Skylake-SP").steppings(0..=4),
+    entry(INTEL, 6, 0x55..=0x55, "Cascade Lake", "Cascade Lake-SP").steppings(5..=7),
+    entry(INTEL, 6, 0x55..=0x55, "Cooper Lake", "Cooper Lake-SP").steppings(10..=11),
+    entry(INTEL, 6, 0x8e..=0x8e, "Skylake", "Kaby Lake").steppings(9..=9),
+    entry(INTEL, 6, 0x8e..=0x8e, "Skylake", "Kaby Lake R").steppings(10..=10),
+    entry(INTEL, 6, 0x8e..=0x8e, "Skylake", "Whiskey Lake").steppings(11..=11),
+    entry(INTEL, 6, 0x8e..=0x8e, "Skylake", "Comet Lake-U").steppings(12..=12),
+    entry(INTEL, 6, 0x9e..=0x9e, "Skylake", "Kaby Lake").steppings(9..=9),
+    entry(INTEL, 6, 0x9e..=0x9e, "Skylake", "Coffee Lake").steppings(10..=13),
+    entry(INTEL, 6, 0xa5..=0xa6, "Skylake", "Comet Lake"),
+    entry(INTEL, 6, 0x66..=0x66, "Palm Cove", "Cannon Lake"),
+    entry(INTEL, 6, 0x7d..=0x7e, "Sunny Cove", "Ice Lake"),
+    entry(INTEL, 6, 0x6a..=0x6a, "Sunny Cove", "Ice Lake-SP"),
+    entry(INTEL, 6, 0x6c..=0x6c, "Sunny Cove", "Ice Lake-D"),
+    entry(INTEL, 6, 0x8c..=0x8d, "Willow Cove", "Tiger Lake"),
+    entry(INTEL, 6, 0xa7..=0xa7, "Cypress Cove", "Rocket Lake"),
+    entry(INTEL, 6, 0x97..=0x97, "Golden Cove", "Alder Lake"),
+    entry(INTEL, 6, 0x9a..=0x9a, "Golden Cove", "Alder Lake-P"),
+    entry(INTEL, 6, 0x8f..=0x8f, "Golden Cove", "Sapphire Rapids"),
+    entry(INTEL, 6, 0xb7..=0xb7, "Raptor Cove", "Raptor Lake"),
+    entry(INTEL, 6, 0xba..=0xba, "Raptor Cove", "Raptor Lake-P"),
+    entry(INTEL, 6, 0xbf..=0xbf, "Raptor Cove", "Raptor Lake-S"),
+    entry(INTEL, 6, 0xcf..=0xcf, "Raptor Cove", "Emerald Rapids"),
+    entry(INTEL, 6, 0xaa..=0xac, "Redwood Cove", "Meteor Lake"),
+    entry(INTEL, 6, 0xad..=0xae, "Redwood Cove", "Granite Rapids"),
+    entry(INTEL, 6, 0xbd..=0xbd, "Lion Cove", "Lunar Lake"),
+    entry(INTEL, 6, 0xc5..=0xc6, "Lion Cove", "Arrow Lake"),
+    // Intel family 6 small cores
+    entry(INTEL, 6, 0x37..=0x37, "Silvermont", "Bay Trail"),
+    entry(INTEL, 6, 0x4d..=0x4d, "Silvermont", "Avoton"),
+    entry(INTEL, 6, 0x4c..=0x4c, "Airmont", "Cherry Trail"),
+    entry(INTEL, 6, 0x5c..=0x5c, "Goldmont", "Apollo Lake"),
+    entry(INTEL, 6, 0x5f..=0x5f, "Goldmont", "Denverton"),
+    entry(INTEL, 6, 0x7a..=0x7a, "Goldmont Plus", "Gemini Lake"),
+    entry(INTEL, 6, 0x86..=0x86, "Tremont", "Snow Ridge"),
+    entry(INTEL, 6, 0x96..=0x96, "Tremont", "Elkhart Lake"),
+    entry(INTEL, 6, 0x9c..=0x9c, "Tremont", "Jasper Lake"),
+    entry(INTEL, 6, 0xbe..=0xbe, "Gracemont", "Alder Lake-N"),
+    entry(INTEL, 6, 0xaf..=0xaf, "Crestmont", "Sierra Forest"),
+    entry(INTEL, 6, 0x57..=0x57, "Knights Landing", "Knights Landing"),
+    entry(INTEL, 6, 0x85..=0x85, "Knights Mill", "Knights Mill"),
+    // AMD family 15h and 16h
+    entry(AMD, 0x15, 0x01..=0x01, "Bulldozer", "Zambezi/Interlagos"),
+    entry(AMD, 0x15, 0x02..=0x02, "Piledriver", "Vishera/Abu Dhabi"),
+    entry(AMD, 0x15, 0x10..=0x1f, "Piledriver", "Trinity/Richland"),
+    entry(AMD, 0x15, 0x30..=0x3f, "Steamroller", "Kaveri"),
+    entry(AMD, 0x15, 0x60..=0x6f, "Excavator", "Carrizo/Bristol Ridge"),
+    entry(AMD, 0x15, 0x70..=0x7f, "Excavator", "Stoney Ridge"),
+    entry(AMD, 0x16, 0x00..=0x0f, "Jaguar", "Kabini/Temash"),
+    entry(AMD, 0x16, 0x30..=0x3f, "Puma", "Beema/Mullins"),
+    // AMD family 17h
+    entry(AMD, 0x17, 0x01..=0x01, "Zen", "Summit Ridge/Naples"),
+    entry(AMD, 0x17, 0x08..=0x08, "Zen+", "Pinnacle Ridge/Colfax"),
+    entry(AMD, 0x17, 0x11..=0x11, "Zen", "Raven Ridge"),
+    entry(AMD, 0x17, 0x18..=0x18, "Zen+", "Picasso"),
+    entry(AMD, 0x17, 0x20..=0x20, "Zen", "Dali"),
+    entry(AMD, 0x17, 0x31..=0x31, "Zen 2", "Rome/Castle Peak"),
+    entry(AMD, 0x17, 0x60..=0x60, "Zen 2", "Renoir"),
+    entry(AMD, 0x17, 0x68..=0x68, "Zen 2", "Lucienne"),
+    entry(AMD, 0x17, 0x71..=0x71, "Zen 2", "Matisse"),
+    entry(AMD, 0x17, 0x90..=0x91, "Zen 2", "Van Gogh"),
+    entry(AMD, 0x17, 0xa0..=0xaf, "Zen 2", "Mendocino"),
+    // AMD family 19h
+    entry(AMD, 0x19, 0x00..=0x01, "Zen 3", "Milan"),
+    entry(AMD, 0x19, 0x08..=0x08, "Zen 3", "Chagall"),
+    entry(AMD, 0x19, 0x21..=0x21, "Zen 3", "Vermeer"),
+    entry(AMD, 0x19, 0x40..=0x44, "Zen 3+", "Rembrandt"),
+    entry(AMD, 0x19, 0x50..=0x5f, "Zen 3", "Cezanne/Barcelo"),
+    entry(AMD, 0x19, 0x10..=0x11, "Zen 4", "Genoa"),
+    entry(AMD, 0x19, 0x18..=0x18, "Zen 4", "Storm Peak"),
+    entry(AMD, 0x19, 0x60..=0x61, "Zen 4", "Raphael"),
+    entry(AMD, 0x19, 0x70..=0x7f, "Zen 4", "Phoenix/Hawk Point"),
+    entry(AMD, 0x19, 0xa0..=0xaf, "Zen 4c", "Bergamo/Siena"),
+    // AMD family 1Ah
+    entry(AMD, 0x1a, 0x00..=0x0f, "Zen 5", "Turin"),
+    entry(AMD, 0x1a, 0x10..=0x1f, "Zen 5c", "Turin Dense"),
+    entry(AMD, 0x1a, 0x20..=0x2f, "Zen 5", "Strix Point"),
+    entry(AMD, 0x1a, 0x40..=0x4f, "Zen 5", "Granite Ridge"),
+    entry(AMD, 0x1a, 0x60..=0x6f, "Zen 5", "Krackan Point"),
+    entry(AMD, 0x1a, 0x70..=0x7f, "Zen 5", "Strix Halo"),
+];
+
+/// Looks a CPUID signature up in [`TABLE`], `None` for CPUs it doesn't know.
+pub fn classify(vendor: &str, family: u32, model: u32, stepping: u32) -> Option<Microarchitecture> {
+    TABLE
+        .iter()
+        .find(|entry| entry.matches(vendor, family, model, stepping))
+        .map(|entry| Microarchitecture {
+            generation: entry.generation.to_string(),
+            codename: entry.codename.to_string(),
+        })
+}
+
+impl CpuIdentity {
+    pub fn microarchitecture(&self) -> Option<Microarchitecture> {
+        classify(&self.vendor, self.family, self.model, self.stepping)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(vendor: &str, family: u32, model: u32, stepping: u32) -> Option<(String, String)> {
+        classify(vendor, family, model, stepping).map(|arch| (arch.generation, arch.codename))
+    }
+
+    fn named(generation: &str, codename: &str) -> Option<(String, String)> {
+        Some((generation.to_string(), codename.to_string()))
+    }
+
+    #[test]
+    fn machines_from_the_readme() {
+        // Xeon Gold 5122
+        assert_eq!(lookup(INTEL, 6, 0x55, 4), named("Skylake", "Skylake-SP"));
+        // Ryzen 5 5500 and Ryzen 7 5800X, one generation
+        assert_eq!(
+            lookup(AMD, 0x19, 0x50, 0),
+            named("Zen 3", "Cezanne/Barcelo")
+        );
+        assert_eq!(lookup(AMD, 0x19, 0x21, 2), named("Zen 3", "Vermeer"));
+    }
+
+    #[test]
+    fn steppings_split_a_model() {
+        assert_eq!(
+            lookup(INTEL, 6, 0x55, 7),
+            named("Cascade Lake", "Cascade Lake-SP")
+        );
+        assert_eq!(
+            lookup(INTEL, 6, 0x55, 11),
+            named("Cooper Lake", "Cooper Lake-SP")
+        );
+        assert_eq!(lookup(INTEL, 6, 0x8e, 10), named("Skylake", "Kaby Lake R"));
+        // no part was sold with these
+        assert_eq!(lookup(INTEL, 6, 0x55, 8), None);
+    }
+
+    #[test]
+    fn unknown_signatures_are_none() {
+        assert_eq!(lookup(INTEL, 6, 0x01, 0), None);
+        assert_eq!(lookup(AMD, 0x20, 0x00, 0), None);
+        // a known signature of another vendor
+        assert_eq!(lookup("HygonGenuine", 0x19, 0x21, 2), None);
+    }
+
+    #[test]
+    fn rows_dont_overlap() {
+        let overlap = |a: &RangeInclusive<u32>, b: &RangeInclusive<u32>| {
+            a.start() <= b.end() && b.start() <= a.end()
+        };
+
+        for (i, row) in TABLE.iter().enumerate() {
+            for other in TABLE[i + 1..].iter() {
+                assert!(
+                    row.vendor != other.vendor
+                        || row.family != other.family
+                        || !overlap(&row.models, &other.models)
+                        || !overlap(&row.steppings, &other.steppings),
+                    "{} and {} match the same signatures",
+                    row.codename,
+                    other.codename
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,286 @@
+//! The SSE control and status register, which sets the rounding mode and subnormal handling of
+//! every scalar and vector floating point instruction on x86_64.
+
+use std::fmt;
+
+#[cfg(target_arch = "x86_64")]
+use std::arch::asm;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::{FingerprintTest, fingerprint_test};
+
+#[cfg(target_arch = "x86_64")]
+const ROUNDING_MASK: u32 = 0b11 << 13;
+const FLUSH_TO_ZERO: u32 = 1 << 15;
+const DENORMALS_ARE_ZERO: u32 = 1 << 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RoundingMode {
+    Nearest,
+    Downward,
+    Upward,
+    TowardZero,
+}
+
+impl RoundingMode {
+    pub const ALL: [Self; 4] = [
+        Self::Nearest,
+        Self::TowardZero,
+        Self::Upward,
+        Self::Downward,
+    ];
+
+    /// The RC field of MXCSR for this mode.
+    pub fn bits(self) -> u32 {
+        let rc = match self {
+            Self::Nearest => 0b00,
+            Self::Downward => 0b01,
+            Self::Upward => 0b10,
+            Self::TowardZero => 0b11,
+        };
+        rc << 13
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Nearest => "to nearest",
+            Self::Downward => "downward",
+            Self::Upward => "upward",
+            Self::TowardZero => "toward zero",
+        }
+    }
+}
+
+/// Flush-to-zero (subnormal results become zero) and denormals-are-zero (subnormal inputs are
+/// read as zero). IEEE-754 behaviour is both off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SubnormalMode {
+    pub ftz: bool,
+    pub daz: bool,
+}
+
+impl SubnormalMode {
+    pub const IEEE: Self = Self {
+        ftz: false,
+        daz: false,
+    };
+
+    pub const ALL: [Self; 4] = [
+        Self::IEEE,
+        Self {
+            ftz: true,
+            daz: false,
+        },
+        Self {
+            ftz: false,
+            daz: true,
+        },
+        Self {
+            ftz: true,
+            daz: true,
+        },
+    ];
+
+    pub fn from_bits(mxcsr: u32) -> Self {
+        Self {
+            ftz: mxcsr & FLUSH_TO_ZERO != 0,
+            daz: mxcsr & DENORMALS_ARE_ZERO != 0,
+        }
+    }
+
+    /// The FTZ and DAZ bits of MXCSR for this mode.
+    pub fn bits(self) -> u32 {
+        let ftz = if self.ftz { FLUSH_TO_ZERO } else { 0 };
+        let daz = if self.daz { DENORMALS_ARE_ZERO } else { 0 };
+        ftz | daz
+    }
+
+    /// The mode of the current thread, `None` where there is no MXCSR.
+    pub fn current() -> Option<Self> {
+        #[cfg(target_arch = "x86_64")]
+        return Some(Self::from_bits(read()));
+
+        #[cfg(not(target_arch = "x86_64"))]
+        return None;
+    }
+}
+
+impl fmt::Display for SubnormalMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = |on| if on { "on" } else { "off" };
+        write!(f, "FTZ {}, DAZ {}", state(self.ftz), state(self.daz))
+    }
+}
+
+/// Fingerprint of a test run under one rounding mode, the default round to nearest included.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoundingFingerprint {
+    pub mode: RoundingMode,
+    pub fingerprint: String,
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn read() -> u32 {
+    let mut val = 0u32;
+    // SAFETY: stores 4 bytes into `val`
+    unsafe { asm!("stmxcsr [{}]", in(reg) &mut val, options(nostack, preserves_flags)) };
+    val
+}
+
+/// Loads `val` into MXCSR.
+///
+/// # Safety
+///
+/// The compiler assumes the default floating point environment, code running while it is
+/// changed must not depend on results the compiler computed at build time. `val` must not set
+/// reserved bits.
+#[cfg(target_arch = "x86_64")]
+pub unsafe fn write(val: u32) {
+    // SAFETY: reads 4 bytes from `val`, the caller vouches for the value
+    unsafe { asm!("ldmxcsr [{}]", in(reg) &val, options(nostack, readonly, preserves_flags)) };
+}
+
+/// Restores MXCSR to the value it had when the guard was created, when dropped or when a panic
+/// unwinds past it.
+#[cfg(target_arch = "x86_64")]
+pub struct MxcsrGuard {
+    saved: u32,
+}
+
+#[cfg(target_arch = "x86_64")]
+impl MxcsrGuard {
+    pub fn new() -> Self {
+        Self { saved: read() }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Default for MxcsrGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl Drop for MxcsrGuard {
+    fn drop(&mut self) {
+        // SAFETY: a value read from MXCSR
+        unsafe { write(self.saved) };
+    }
+}
+
+/// Fingerprint of a test run under one FTZ/DAZ combination.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SubnormalFingerprint {
+    pub mode: SubnormalMode,
+    pub fingerprint: String,
+}
+
+// Runs `f` with the `mask` bits of MXCSR replaced by `bits`, then restores it
+#[cfg(target_arch = "x86_64")]
+fn with_fields<R>(mask: u32, bits: u32, f: impl FnOnce() -> R) -> R {
+    let guard = MxcsrGuard::new();
+    // SAFETY: only control bits change; the tests hide their inputs from constant folding
+    unsafe { write(guard.saved & !mask | bits) };
+    f()
+}
+
+/// Runs `f` with the SSE rounding mode set to `mode`, then restores MXCSR.
+#[cfg(target_arch = "x86_64")]
+pub fn with_rounding_mode<R>(mode: RoundingMode, f: impl FnOnce() -> R) -> R {
+    with_fields(ROUNDING_MASK, mode.bits(), f)
+}
+
+/// Runs `f` with FTZ and DAZ set as in `mode`, then restores MXCSR.
+#[cfg(target_arch = "x86_64")]
+pub fn with_subnormal_mode<R>(mode: SubnormalMode, f: impl FnOnce() -> R) -> R {
+    with_fields(FLUSH_TO_ZERO | DENORMALS_ARE_ZERO, mode.bits(), f)
+}
+
+/// Runs `test` once under every [`RoundingMode`] and fingerprints each run. Tests that don't
+/// [follow MXCSR](FingerprintTest::follows_mxcsr) give the same fingerprint in every mode.
+pub fn rounding_sweep(test: &dyn FingerprintTest) -> Result<Vec<RoundingFingerprint>> {
+    #[cfg(target_arch = "x86_64")]
+    return RoundingMode::ALL
+        .iter()
+        .map(|&mode| {
+            let results = with_rounding_mode(mode, || test.run())?;
+            let (fingerprint, _) = fingerprint_test(test, &results);
+            Ok(RoundingFingerprint { mode, fingerprint })
+        })
+        .collect();
+
+    #[cfg(not(target_arch = "x86_64"))]
+    return Err(crate::FingerprintError::UnsupportedCpuFeature {
+        test: test.id().to_string(),
+        feature: "x86_64 MXCSR".to_string(),
+    });
+}
+
+/// Runs `test` once under every FTZ/DAZ combination and fingerprints each run, see
+/// [`rounding_sweep`] for tests that don't follow MXCSR.
+pub fn subnormal_sweep(test: &dyn FingerprintTest) -> Result<Vec<SubnormalFingerprint>> {
+    #[cfg(target_arch = "x86_64")]
+    return SubnormalMode::ALL
+        .iter()
+        .map(|&mode| {
+            let results = with_subnormal_mode(mode, || test.run())?;
+            let (fingerprint, _) = fingerprint_test(test, &results);
+            Ok(SubnormalFingerprint { mode, fingerprint })
+        })
+        .collect();
+
+    #[cfg(not(target_arch = "x86_64"))]
+    return Err(crate::FingerprintError::UnsupportedCpuFeature {
+        test: test.id().to_string(),
+        feature: "x86_64 MXCSR".to_string(),
+    });
+}
+
+#[cfg(all(test, target_arch = "x86_64"))]
+mod tests {
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    use super::*;
+
+    #[test]
+    fn a_panic_restores_mxcsr() {
+        let before = read();
+        let mut during = before;
+
+        let unwound = catch_unwind(AssertUnwindSafe(|| {
+            with_rounding_mode(RoundingMode::Upward, || {
+                during = read();
+                panic!("a test panicked mid sweep");
+            })
+        }));
+
+        assert!(unwound.is_err());
+        assert_eq!(during & ROUNDING_MASK, RoundingMode::Upward.bits());
+        assert_eq!(read(), before);
+    }
+
+    #[test]
+    fn a_panic_restores_the_subnormal_mode() {
+        let before = read();
+        let all = SubnormalMode {
+            ftz: true,
+            daz: true,
+        };
+        let mut during = SubnormalMode::IEEE;
+
+        let unwound = catch_unwind(AssertUnwindSafe(|| {
+            with_subnormal_mode(all, || {
+                during = SubnormalMode::from_bits(read());
+                panic!("a test panicked mid sweep");
+            })
+        }));
+
+        assert!(unwound.is_err());
+        assert_eq!(during, all);
+        assert_eq!(read(), before);
+    }
+}
@@ -0,0 +1,162 @@
+//! Fingerprints of every test on every logical CPU, to find cores that compute differently.
+
+use serde::{Deserialize, Serialize};
+
+use crate::affinity::{allowed_cpus, run_pinned};
+use crate::error::{FingerprintError, Result};
+use crate::topology::cpu_topology_id;
+use crate::{FingerprintTest, fingerprint_test};
+
+/// Results of one logical CPU.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoreReport {
+    pub cpu: usize,
+    /// Physical package id, `None` if the kernel doesn't report it.
+    pub socket: Option<u32>,
+    /// Core id within the socket, shared by SMT siblings.
+    pub core: Option<u32>,
+    /// One fingerprint per test, in the order the tests ran.
+    pub fingerprints: Vec<CoreFingerprint>,
+    /// Ids of the tests where this CPU disagrees with the majority of CPUs, or no fingerprint has
+    /// a majority.
+    #[serde(default)]
+    pub outliers: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CoreFingerprint {
+    pub test: String,
+    pub fingerprint: String,
+}
+
+/// Runs every test once on a worker thread pinned to each allowed logical CPU in turn, sorted
+/// by socket and core, with disagreeing CPUs flagged. Tests that pin their own threads are
+/// skipped.
+pub fn run_per_core(tests: &[Box<dyn FingerprintTest>]) -> Result<Vec<CoreReport>> {
+    let cpus = allowed_cpus().map_err(|source| FingerprintError::Affinity { cpu: None, source })?;
+    let mut cores = Vec::with_capacity(cpus.len());
+
+    for cpu in cpus {
+        let fingerprints = run_pinned(cpu, || {
+            tests
+                .iter()
+                .filter(|test| !test.pins_threads())
+                .map(|test| {
+                    let results = test.run()?;
+                    let (fingerprint, _) = fingerprint_test(test.as_ref(), &results);
+                    Ok(CoreFingerprint {
+                        test: test.id().to_string(),
+                        fingerprint,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+        })??;
+
+        cores.push(CoreReport {
+            cpu,
+            socket: cpu_topology_id(cpu, "physical_package_id"),
+            core: cpu_topology_id(cpu, "core_id"),
+            fingerprints,
+            outliers: Vec::new(),
+        });
+    }
+
+    cores.sort_by_key(|core| (core.socket, core.core, core.cpu));
+    flag_outliers(&mut cores);
+
+    Ok(cores)
+}
+
+/// Marks, per test, every CPU whose fingerprint differs from the most common one. When several
+/// fingerprints are equally common there is no majority and every CPU is marked.
+pub fn flag_outliers(cores: &mut [CoreReport]) {
+    let Some(first) = cores.first() else {
+        return;
+    };
+    let tests: Vec<String> = first.fingerprints.iter().map(|f| f.test.clone()).collect();
+
+    for test in tests {
+        let fingerprint_of = |core: &CoreReport| {
+            core.fingerprints
+                .iter()
+                .find(|f| f.test == test)
+                .map(|f| f.fingerprint.clone())
+        };
+
+        let mut counts: Vec<(Option<String>, usize)> = Vec::new();
+        for core in cores.iter() {
+            let fingerprint = fingerprint_of(core);
+            match counts.iter_mut().find(|(f, _)| *f == fingerprint) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((fingerprint, 1)),
+            }
+        }
+
+        let most = counts.iter().map(|(_, count)| *count).max().unwrap_or(0);
+        let mut most_common = counts.iter().filter(|(_, count)| *count == most);
+        let majority = match (most_common.next(), most_common.next()) {
+            (Some((fingerprint, _)), None) => Some(fingerprint.clone()),
+            _ => None,
+        };
+
+        for core in cores.iter_mut() {
+            if majority.as_ref() != Some(&fingerprint_of(core)) {
+                core.outliers.push(test.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn core(cpu: usize, fingerprint: &str) -> CoreReport {
+        CoreReport {
+            cpu,
+            socket: Some(0),
+            core: Some(cpu as u32),
+            fingerprints: vec![CoreFingerprint {
+                test: "fma".to_string(),
+                fingerprint: fingerprint.to_string(),
+            }],
+            outliers: Vec::new(),
+        }
+    }
+
+    fn flagged(cores: &[CoreReport]) -> Vec<usize> {
+        cores
+            .iter()
+            .filter(|core| !core.outliers.is_empty())
+            .map(|core| core.cpu)
+            .collect()
+    }
+
+    #[test]
+    fn a_single_disagreeing_cpu_is_flagged() {
+        let mut cores = vec![core(0, "a"), core(1, "a"), core(2, "b"), core(3, "a")];
+        flag_outliers(&mut cores);
+
+        assert_eq!(flagged(&cores), [2]);
+        assert_eq!(cores[2].outliers, ["fma"]);
+    }
+
+    #[test]
+    fn an_even_split_flags_every_cpu() {
+        let mut cores = vec![core(0, "a"), core(1, "b")];
+        flag_outliers(&mut cores);
+        assert_eq!(flagged(&cores), [0, 1]);
+
+        let mut cores = vec![core(0, "a"), core(1, "a"), core(2, "b"), core(3, "b")];
+        flag_outliers(&mut cores);
+        assert_eq!(flagged(&cores), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn agreeing_cpus_are_not_flagged() {
+        let mut cores = vec![core(0, "a"), core(1, "a")];
+        flag_outliers(&mut cores);
+
+        assert!(flagged(&cores).is_empty());
+    }
+}
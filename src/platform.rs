@@ -0,0 +1,128 @@
+//! Software and firmware versions that can change results without the silicon changing.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Microcode revision of one logical CPU.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MicrocodeRevision {
+    pub cpu: usize,
+    /// As the kernel reports it, e.g. `"0x2b000603"`.
+    pub revision: String,
+}
+
+/// Microcode revision of every logical CPU the kernel reports one for, in CPU order.
+///
+/// Reads `/sys/devices/system/cpu/cpu*/microcode/version` and falls back to the `microcode`
+/// lines of `/proc/cpuinfo` for CPUs without it. Empty outside Linux.
+pub fn microcode_revisions() -> Vec<MicrocodeRevision> {
+    let mut revisions = cpuinfo_microcode(Path::new("/proc/cpuinfo"));
+
+    for revision in revisions.iter_mut() {
+        let path = format!(
+            "/sys/devices/system/cpu/cpu{}/microcode/version",
+            revision.cpu
+        );
+        if let Ok(version) = fs::read_to_string(path) {
+            revision.revision = version.trim().to_string();
+        }
+    }
+
+    if revisions.is_empty() {
+        revisions = sysfs_microcode(Path::new("/sys/devices/system/cpu"));
+    }
+
+    revisions
+}
+
+fn cpuinfo_microcode(path: &Path) -> Vec<MicrocodeRevision> {
+    let Ok(cpuinfo) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+
+    let mut revisions = Vec::new();
+    let mut cpu = None;
+
+    for line in cpuinfo.lines() {
+        let Some((key, val)) = line.split_once(':') else {
+            continue;
+        };
+
+        match key.trim() {
+            "processor" => cpu = val.trim().parse().ok(),
+            "microcode" => {
+                if let Some(cpu) = cpu {
+                    revisions.push(MicrocodeRevision {
+                        cpu,
+                        revision: val.trim().to_string(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    revisions
+}
+
+fn sysfs_microcode(cpu_dir: &Path) -> Vec<MicrocodeRevision> {
+    let Ok(entries) = fs::read_dir(cpu_dir) else {
+        return Vec::new();
+    };
+
+    let mut revisions: Vec<MicrocodeRevision> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let cpu = entry
+                .file_name()
+                .to_str()?
+                .strip_prefix("cpu")?
+                .parse()
+                .ok()?;
+            let version = fs::read_to_string(entry.path().join("microcode/version")).ok()?;
+
+            Some(MicrocodeRevision {
+                cpu,
+                revision: version.trim().to_string(),
+            })
+        })
+        .collect();
+
+    revisions.sort_by_key(|revision| revision.cpu);
+    revisions
+}
+
+/// The distinct revisions in `revisions`, comma separated, `None` if there are none.
+pub fn microcode_summary(revisions: &[MicrocodeRevision]) -> Option<String> {
+    let mut distinct: Vec<&str> = Vec::new();
+
+    for revision in revisions {
+        if !distinct.contains(&revision.revision.as_str()) {
+            distinct.push(&revision.revision);
+        }
+    }
+
+    (!distinct.is_empty()).then(|| distinct.join(", "))
+}
+
+/// The running kernel's release, e.g. `"6.8.0-45-generic"`.
+pub fn kernel_release() -> Option<String> {
+    fs::read_to_string("/proc/sys/kernel/osrelease")
+        .ok()
+        .map(|release| release.trim().to_string())
+}
+
+/// Name and version of the C library providing libm, e.g. `"glibc 2.39"`.
+pub fn libm_version() -> Option<String> {
+    #[cfg(all(target_os = "linux", target_env = "gnu"))]
+    {
+        // SAFETY: returns a pointer to a static NUL terminated string
+        let version = unsafe { std::ffi::CStr::from_ptr(libc::gnu_get_libc_version()) };
+        Some(format!("glibc {}", version.to_string_lossy()))
+    }
+
+    #[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+    None
+}
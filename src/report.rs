@@ -0,0 +1,505 @@
+//! Machine readable report of a fingerprinting session.
+
+use std::env::consts;
+use std::fmt;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cpuid::{self, CpuIdentity};
+use crate::error::{FingerprintError, Result};
+use crate::hybrid::{self, CoreClass};
+use crate::microarch::Microarchitecture;
+use crate::mxcsr::{RoundingFingerprint, SubnormalFingerprint, SubnormalMode};
+use crate::per_core::CoreReport;
+use crate::platform::{self, MicrocodeRevision};
+use crate::softfloat::Conformance;
+use crate::topology::Topology;
+use crate::{FingerprintTest, SubFingerprint, fingerprint_test};
+
+/// Version of the JSON layout of [`Report`], bumped on incompatible changes.
+pub const REPORT_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    pub schema_version: u32,
+    pub tool: ToolInfo,
+    pub system: SystemInfo,
+    pub consistency_runs: usize,
+    pub sample_size: usize,
+    /// FTZ/DAZ state of the process when the tests ran, `None` without MXCSR.
+    #[serde(default)]
+    pub subnormal_mode: Option<SubnormalMode>,
+    pub tests: Vec<TestReport>,
+    /// Fingerprints per logical CPU, recorded with `--per-core`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub per_core: Vec<CoreReport>,
+}
+
+/// Why [`Report::from_json`] rejected a report.
+#[derive(Debug)]
+pub enum ReportError {
+    Json(serde_json::Error),
+    /// The report has another `schema_version`, or none.
+    UnsupportedSchema(Option<u64>),
+}
+
+/// The build of this crate that produced a report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolInfo {
+    pub name: String,
+    pub version: String,
+    pub profile: String,
+    pub target_arch: String,
+    pub target_os: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SystemInfo {
+    pub os: String,
+    pub arch: String,
+    /// CPUs available to this process, which cgroup limits and affinity can lower.
+    pub logical_cpus: usize,
+    /// `None` outside Linux.
+    #[serde(default)]
+    pub topology: Option<Topology>,
+    /// `None` on architectures without CPUID.
+    #[serde(default)]
+    pub cpu: Option<CpuIdentity>,
+    /// [`CpuIdentity::microarchitecture`], `None` if the CPU isn't in the table.
+    #[serde(default)]
+    pub microarchitecture: Option<Microarchitecture>,
+    /// Per logical CPU, empty where the kernel doesn't report it.
+    #[serde(default)]
+    pub microcode: Vec<MicrocodeRevision>,
+    #[serde(default)]
+    pub kernel: Option<String>,
+    #[serde(default)]
+    pub libm: Option<String>,
+    /// See [`crate::hybrid::detect_core_classes`], a single class on CPUs that aren't hybrid.
+    #[serde(default)]
+    pub core_classes: Vec<CoreClass>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestReport {
+    pub id: String,
+    pub name: String,
+    pub version: u32,
+    /// See [`FingerprintTest::notes`], collected after the first run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Fingerprint of the first run.
+    pub fingerprint: String,
+    /// Sub-fingerprints of the first run, empty for tests without [`FingerprintTest::sub_results`].
+    #[serde(default)]
+    pub sub_fingerprints: Vec<SubFingerprint>,
+    /// Fingerprint of every run, in order.
+    pub run_fingerprints: Vec<String>,
+    /// How often each distinct fingerprint occurred, in order of first occurrence.
+    pub consistency: Vec<FingerprintCount>,
+    /// Results of the first run, serialized as the hex of `f64::to_bits`.
+    #[serde(with = "hex_bits")]
+    pub raw_results: Vec<f64>,
+    /// The same test run on [`crate::math::PortableMath`], recorded in attribution mode.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub portable: Option<Box<TestReport>>,
+    /// The first run checked against the IEEE-754 software reference, see [`crate::softfloat`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<ReferenceCheck>,
+    /// One run under each rounding mode, recorded with `--rounding-sweep`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rounding: Vec<RoundingFingerprint>,
+    /// One run under each FTZ/DAZ combination, recorded with `--subnormal-sweep`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub subnormal: Vec<SubnormalFingerprint>,
+    /// One run pinned to each core class, recorded on hybrid CPUs.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub core_classes: Vec<SubFingerprint>,
+    /// [`TestReport::core_classes`] combined, the same whichever core the run was scheduled on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub composite_fingerprint: Option<String>,
+}
+
+/// Result by result comparison of a hardware run with the same test on [`crate::softfloat`].
+///
+/// The reference calls the same libm as the hardware run, so a deviation means the FPU rounded
+/// a basic operation differently from IEEE-754.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReferenceCheck {
+    /// Fingerprint of the reference results.
+    pub fingerprint: String,
+    /// Number of results that match the reference.
+    pub exact: usize,
+    /// Every result that doesn't, in index order.
+    pub deviations: Vec<Deviation>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deviation {
+    pub index: usize,
+    /// Distance to the reference, `None` when only one side is NaN.
+    pub ulps: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FingerprintCount {
+    pub fingerprint: String,
+    pub count: usize,
+}
+
+impl ToolInfo {
+    pub fn current() -> Self {
+        Self {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            profile: if cfg!(debug_assertions) {
+                "debug"
+            } else {
+                "release"
+            }
+            .to_string(),
+            target_arch: consts::ARCH.to_string(),
+            target_os: consts::OS.to_string(),
+        }
+    }
+}
+
+impl SystemInfo {
+    pub fn current() -> Self {
+        let cpu = cpuid::identify();
+
+        Self {
+            os: consts::OS.to_string(),
+            arch: consts::ARCH.to_string(),
+            logical_cpus: num_cpus::get(),
+            topology: Topology::current(),
+            cpu: cpu.clone(),
+            microarchitecture: cpu.as_ref().and_then(CpuIdentity::microarchitecture),
+            microcode: platform::microcode_revisions(),
+            kernel: platform::kernel_release(),
+            libm: platform::libm_version(),
+            core_classes: hybrid::detect_core_classes(),
+        }
+    }
+}
+
+// Derived equality would never match a report holding a NaN result, so raw results compare
+// by their bits, the same way they are fingerprinted.
+impl PartialEq for TestReport {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            id,
+            name,
+            version,
+            notes,
+            fingerprint,
+            sub_fingerprints,
+            run_fingerprints,
+            consistency,
+            raw_results,
+            portable,
+            reference,
+            rounding,
+            subnormal,
+            core_classes,
+            composite_fingerprint,
+        } = self;
+
+        *id == other.id
+            && *name == other.name
+            && *version == other.version
+            && *notes == other.notes
+            && *fingerprint == other.fingerprint
+            && *sub_fingerprints == other.sub_fingerprints
+            && *run_fingerprints == other.run_fingerprints
+            && *consistency == other.consistency
+            && raw_results.len() == other.raw_results.len()
+            && raw_results
+                .iter()
+                .zip(other.raw_results.iter())
+                .all(|(a, b)| a.to_bits() == b.to_bits())
+            && *portable == other.portable
+            && *reference == other.reference
+            && *rounding == other.rounding
+            && *subnormal == other.subnormal
+            && *core_classes == other.core_classes
+            && *composite_fingerprint == other.composite_fingerprint
+    }
+}
+
+impl TestReport {
+    /// An empty report for `test`, filled in with [`TestReport::add_run`].
+    pub fn new(test: &dyn FingerprintTest) -> Self {
+        Self {
+            id: test.id().to_string(),
+            name: test.name().to_string(),
+            version: test.version(),
+            notes: Vec::new(),
+            fingerprint: String::new(),
+            sub_fingerprints: Vec::new(),
+            run_fingerprints: Vec::new(),
+            consistency: Vec::new(),
+            raw_results: Vec::new(),
+            portable: None,
+            reference: None,
+            rounding: Vec::new(),
+            subnormal: Vec::new(),
+            core_classes: Vec::new(),
+            composite_fingerprint: None,
+        }
+    }
+
+    /// Runs `test` `runs` times and records the fingerprint of each run.
+    pub fn collect(test: &dyn FingerprintTest, runs: usize) -> Result<Self> {
+        if runs == 0 {
+            return Err(FingerprintError::InvalidConfiguration(
+                "a test has to run at least once".to_string(),
+            ));
+        }
+
+        let mut report = Self::new(test);
+
+        for run in 1..=runs {
+            let results = test.run()?;
+            report.add_run(test, &results);
+
+            if run == 1 {
+                report.raw_results = results;
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Records the fingerprint of one more run of `test`.
+    pub fn add_run(&mut self, test: &dyn FingerprintTest, results: &[f64]) -> &str {
+        let (fingerprint, sub_fingerprints) = fingerprint_test(test, results);
+
+        if self.run_fingerprints.is_empty() {
+            self.notes = test.notes();
+            self.fingerprint = fingerprint.clone();
+            self.sub_fingerprints = sub_fingerprints;
+        }
+
+        match self
+            .consistency
+            .iter_mut()
+            .find(|entry| entry.fingerprint == fingerprint)
+        {
+            Some(entry) => entry.count += 1,
+            None => self.consistency.push(FingerprintCount {
+                fingerprint: fingerprint.clone(),
+                count: 1,
+            }),
+        }
+
+        self.run_fingerprints.push(fingerprint);
+        self.run_fingerprints.last().unwrap()
+    }
+
+    /// The FTZ/DAZ combinations of the subnormal sweep that reproduce the default run.
+    pub fn subnormal_matches(&self) -> Vec<SubnormalMode> {
+        self.subnormal
+            .iter()
+            .filter(|entry| entry.fingerprint == self.fingerprint)
+            .map(|entry| entry.mode)
+            .collect()
+    }
+
+    /// The composite fingerprint where core classes were fingerprinted, otherwise the first run.
+    pub fn stable_fingerprint(&self) -> &str {
+        self.composite_fingerprint
+            .as_deref()
+            .unwrap_or(&self.fingerprint)
+    }
+
+    /// True when every run produced the same fingerprint.
+    pub fn is_consistent(&self) -> bool {
+        self.consistency.len() == 1
+    }
+}
+
+impl ReferenceCheck {
+    /// Runs `reference`, a test built with [`crate::softfloat::Arithmetic::SoftFloat`], and
+    /// classifies each of the `hardware` results against it.
+    pub fn run(reference: &dyn FingerprintTest, hardware: &[f64]) -> Result<Self> {
+        let expected = reference.run()?;
+        let (fingerprint, _) = fingerprint_test(reference, &expected);
+
+        let mut exact = 0;
+        let mut deviations = Vec::new();
+
+        for (index, (&hw, &sw)) in hardware.iter().zip(expected.iter()).enumerate() {
+            match Conformance::classify(hw, sw) {
+                Conformance::Exact => exact += 1,
+                Conformance::Deviates(ulps) => deviations.push(Deviation { index, ulps }),
+            }
+        }
+
+        Ok(Self {
+            fingerprint,
+            exact,
+            deviations,
+        })
+    }
+
+    /// The classification of the result at `index`.
+    pub fn conformance(&self, index: usize) -> Conformance {
+        match self.deviations.iter().find(|dev| dev.index == index) {
+            Some(dev) => Conformance::Deviates(dev.ulps),
+            None => Conformance::Exact,
+        }
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.deviations.is_empty()
+    }
+}
+
+impl Report {
+    pub fn new(consistency_runs: usize, sample_size: usize) -> Self {
+        Self {
+            schema_version: REPORT_SCHEMA_VERSION,
+            tool: ToolInfo::current(),
+            system: SystemInfo::current(),
+            consistency_runs,
+            sample_size,
+            subnormal_mode: SubnormalMode::current(),
+            tests: Vec::new(),
+            per_core: Vec::new(),
+        }
+    }
+
+    pub fn test(&self, id: &str) -> Option<&TestReport> {
+        self.tests.iter().find(|test| test.id == id)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a report always serializes")
+    }
+
+    /// Parses a report, rejecting any schema version other than [`REPORT_SCHEMA_VERSION`].
+    pub fn from_json(json: &str) -> std::result::Result<Self, ReportError> {
+        let value: serde_json::Value = serde_json::from_str(json).map_err(ReportError::Json)?;
+
+        // checked first, a report of another version may not deserialize at all
+        let version = value.get("schema_version").and_then(|v| v.as_u64());
+        if version != Some(REPORT_SCHEMA_VERSION as u64) {
+            return Err(ReportError::UnsupportedSchema(version));
+        }
+
+        serde_json::from_value(value).map_err(ReportError::Json)
+    }
+
+    /// Reads and parses the JSON report at `path`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(|err| FingerprintError::io(path, err))?;
+
+        Self::from_json(&json).map_err(|source| FingerprintError::InvalidReport {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    /// Writes the report to `path` as JSON.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_json()).map_err(|err| FingerprintError::io(path, err))
+    }
+}
+
+impl fmt::Display for ReportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Json(source) => source.fmt(f),
+            Self::UnsupportedSchema(Some(version)) => write!(
+                f,
+                "schema version {} is not supported, expected {}",
+                version, REPORT_SCHEMA_VERSION
+            ),
+            Self::UnsupportedSchema(None) => write!(f, "no schema version"),
+        }
+    }
+}
+
+impl std::error::Error for ReportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(source) => Some(source),
+            Self::UnsupportedSchema(_) => None,
+        }
+    }
+}
+
+mod hex_bits {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(values: &[f64], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(values.iter().map(|val| format!("{:016x}", val.to_bits())))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|hex| {
+                u64::from_str_radix(hex, 16)
+                    .map(f64::from_bits)
+                    .map_err(|_| D::Error::custom(format!("invalid f64 bits {:?}", hex)))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::suite::NanPropagationTest;
+
+    fn nan_report() -> Report {
+        let mut report = Report::new(1, 16);
+        report
+            .tests
+            .push(TestReport::collect(&NanPropagationTest, 1).unwrap());
+        report
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let report = nan_report();
+        assert!(report.tests[0].raw_results.iter().any(|r| r.is_nan()));
+
+        assert_eq!(Report::from_json(&report.to_json()).unwrap(), report);
+    }
+
+    #[test]
+    fn raw_results_compare_by_bits() {
+        let report = nan_report();
+        let mut other = report.clone();
+        let first_nan = other.tests[0]
+            .raw_results
+            .iter()
+            .position(|r| r.is_nan())
+            .unwrap();
+        other.tests[0].raw_results[first_nan] = -other.tests[0].raw_results[first_nan];
+
+        assert_ne!(report, other);
+    }
+
+    #[test]
+    fn rejects_other_schema_versions() {
+        let mut json: serde_json::Value = serde_json::from_str(&nan_report().to_json()).unwrap();
+        json["schema_version"] = 99.into();
+
+        assert!(matches!(
+            Report::from_json(&json.to_string()),
+            Err(ReportError::UnsupportedSchema(Some(99)))
+        ));
+
+        json.as_object_mut().unwrap().remove("schema_version");
+        assert!(matches!(
+            Report::from_json(&json.to_string()),
+            Err(ReportError::UnsupportedSchema(None))
+        ));
+    }
+}
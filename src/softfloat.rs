@@ -0,0 +1,458 @@
+//! Bit-exact software implementation of IEEE-754 binary64 `+ - * /` with round-to-nearest-even.
+//!
+//! Used as the reference the hardware results are checked against, so it deliberately uses only
+//! integer arithmetic. Subnormal inputs and outputs are handled exactly; NaN results carry the
+//! quieted payload of the first NaN operand, or the positive quiet NaN for invalid operations.
+
+use std::fmt;
+use std::ops::{Add, Div, Mul, Sub};
+
+use serde::{Deserialize, Serialize};
+
+use crate::compare::ulp_distance;
+
+const SIGN: u64 = 1 << 63;
+const EXP_MASK: u64 = 0x7ff0_0000_0000_0000;
+const FRAC_MASK: u64 = (1 << 52) - 1;
+const QUIET: u64 = 1 << 51;
+const DEFAULT_NAN: u64 = 0x7ff8_0000_0000_0000;
+
+/// Which implementation of the basic operations a test runs with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Arithmetic {
+    /// The CPU's floating point unit.
+    #[default]
+    Hardware,
+    /// [`SoftF64`], the IEEE-754 reference.
+    SoftFloat,
+}
+
+/// The number type a test kernel computes with, `f64` or [`SoftF64`].
+pub trait Float:
+    Copy + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> + Div<Output = Self>
+{
+    fn from_f64(val: f64) -> Self;
+    fn to_f64(self) -> f64;
+}
+
+impl Float for f64 {
+    fn from_f64(val: f64) -> Self {
+        val
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
+}
+
+/// An `f64` whose arithmetic operators go through this module instead of the FPU.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SoftF64(pub f64);
+
+impl Float for SoftF64 {
+    fn from_f64(val: f64) -> Self {
+        Self(val)
+    }
+
+    fn to_f64(self) -> f64 {
+        self.0
+    }
+}
+
+impl Add for SoftF64 {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self(add(self.0, rhs.0))
+    }
+}
+
+impl Sub for SoftF64 {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(sub(self.0, rhs.0))
+    }
+}
+
+impl Mul for SoftF64 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self {
+        Self(mul(self.0, rhs.0))
+    }
+}
+
+impl Div for SoftF64 {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self {
+        Self(div(self.0, rhs.0))
+    }
+}
+
+/// How a hardware result relates to the software reference for the same computation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conformance {
+    /// Bit for bit the same, or NaN on both sides.
+    Exact,
+    /// Off by this many ulp, `None` when only one side is NaN.
+    Deviates(Option<u64>),
+}
+
+impl Conformance {
+    pub fn classify(hardware: f64, reference: f64) -> Self {
+        if hardware.to_bits() == reference.to_bits() || (hardware.is_nan() && reference.is_nan()) {
+            Self::Exact
+        } else {
+            Self::Deviates(ulp_distance(hardware, reference))
+        }
+    }
+}
+
+impl fmt::Display for Conformance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exact => write!(f, "matches reference"),
+            Self::Deviates(Some(ulps)) => write!(f, "deviates by {} ULP", ulps),
+            Self::Deviates(None) => write!(f, "deviates (NaN)"),
+        }
+    }
+}
+
+// A finite non-zero value as m * 2^e with the leading bit of m at position 52
+struct Unpacked {
+    negative: bool,
+    m: u64,
+    e: i32,
+}
+
+fn unpack(bits: u64) -> Unpacked {
+    let negative = bits & SIGN != 0;
+    let exp = ((bits & EXP_MASK) >> 52) as i32;
+    let frac = bits & FRAC_MASK;
+
+    let (mut m, mut e) = if exp == 0 {
+        (frac, -1074)
+    } else {
+        (frac | (1 << 52), exp - 1075)
+    };
+
+    let shift = m.leading_zeros() as i32 - 11;
+    m <<= shift;
+    e -= shift;
+
+    Unpacked { negative, m, e }
+}
+
+fn is_nan(bits: u64) -> bool {
+    bits & !SIGN > EXP_MASK
+}
+
+fn is_inf(bits: u64) -> bool {
+    bits & !SIGN == EXP_MASK
+}
+
+fn is_zero(bits: u64) -> bool {
+    bits & !SIGN == 0
+}
+
+fn propagate_nan(a: u64, b: u64) -> f64 {
+    let nan = if is_nan(a) { a } else { b };
+    f64::from_bits(nan | QUIET)
+}
+
+fn signed(negative: bool, bits: u64) -> f64 {
+    f64::from_bits(if negative { bits | SIGN } else { bits })
+}
+
+/// Rounds `(m + s) * 2^e` to the nearest `f64`, ties to even, where `0 < s < 1` if `sticky`
+/// and `s == 0` otherwise.
+fn round(negative: bool, mut m: u128, mut e: i32, sticky: bool) -> f64 {
+    if m == 0 {
+        return signed(negative, 0);
+    }
+
+    // keep plenty of bits below the rounding position so `sticky` is always below the half ulp
+    let lz = m.leading_zeros() as i32;
+    if lz > 20 {
+        m <<= lz - 20;
+        e -= lz - 20;
+    }
+
+    let msb = 127 - m.leading_zeros() as i32;
+    let exponent = msb + e;
+
+    // number of low bits of m that don't fit in the result
+    let shift = if exponent >= -1022 {
+        msb - 52
+    } else {
+        -1074 - e
+    };
+
+    if shift >= 128 {
+        // far below half the smallest subnormal
+        return signed(negative, 0);
+    }
+
+    let kept = (m >> shift) as u64;
+    let rest = m & ((1u128 << shift) - 1);
+    let half = 1u128 << (shift - 1);
+    let round_up = rest > half || (rest == half && (sticky || kept & 1 == 1));
+    let kept = kept + round_up as u64;
+
+    let bits = if exponent >= -1022 {
+        // kept is in [2^52, 2^53], a carry out of the significand bumps the exponent
+        (((exponent + 1022) as u64) << 52) + kept
+    } else {
+        // subnormal, rounding up to 2^52 yields the smallest normal
+        kept
+    };
+
+    if bits >= EXP_MASK {
+        return signed(negative, EXP_MASK);
+    }
+
+    signed(negative, bits)
+}
+
+pub fn add(a: f64, b: f64) -> f64 {
+    let (a, b) = (a.to_bits(), b.to_bits());
+
+    if is_nan(a) || is_nan(b) {
+        return propagate_nan(a, b);
+    }
+    if is_inf(a) || is_inf(b) {
+        if is_inf(a) && is_inf(b) && (a ^ b) & SIGN != 0 {
+            return f64::from_bits(DEFAULT_NAN);
+        }
+        return f64::from_bits(if is_inf(a) { a } else { b });
+    }
+    if is_zero(a) || is_zero(b) {
+        if is_zero(a) && is_zero(b) {
+            // -0 + -0 = -0, every other sum of zeros is +0
+            return f64::from_bits(a & b);
+        }
+        return f64::from_bits(if is_zero(a) { b } else { a });
+    }
+
+    let (mut x, mut y) = (unpack(a), unpack(b));
+    if x.e < y.e {
+        std::mem::swap(&mut x, &mut y);
+    }
+
+    // x gets 64 spare low bits, y is aligned to it and truncated with a sticky flag
+    let e = x.e - 64;
+    let big = (x.m as u128) << 64;
+    let d = x.e - y.e;
+    let (small, sticky) = if d <= 64 {
+        ((y.m as u128) << (64 - d), false)
+    } else if d < 128 {
+        let shift = d - 64;
+        (
+            (y.m as u128) >> shift,
+            (y.m as u128) & ((1 << shift) - 1) != 0,
+        )
+    } else {
+        (0, true)
+    };
+
+    if x.negative == y.negative {
+        return round(x.negative, big + small, e, sticky);
+    }
+
+    // the truncated part of `small` is subtracted too, so borrow one unit and keep it sticky
+    let small = small + sticky as u128;
+    match big.cmp(&small) {
+        std::cmp::Ordering::Greater => round(x.negative, big - small, e, sticky),
+        std::cmp::Ordering::Less => round(y.negative, small - big, e, sticky),
+        std::cmp::Ordering::Equal => 0.0,
+    }
+}
+
+pub fn sub(a: f64, b: f64) -> f64 {
+    if b.is_nan() {
+        return propagate_nan(a.to_bits(), b.to_bits());
+    }
+    add(a, f64::from_bits(b.to_bits() ^ SIGN))
+}
+
+pub fn mul(a: f64, b: f64) -> f64 {
+    let (a, b) = (a.to_bits(), b.to_bits());
+    let negative = (a ^ b) & SIGN != 0;
+
+    if is_nan(a) || is_nan(b) {
+        return propagate_nan(a, b);
+    }
+    if is_inf(a) || is_inf(b) {
+        if is_zero(a) || is_zero(b) {
+            return f64::from_bits(DEFAULT_NAN);
+        }
+        return signed(negative, EXP_MASK);
+    }
+    if is_zero(a) || is_zero(b) {
+        return signed(negative, 0);
+    }
+
+    let (x, y) = (unpack(a), unpack(b));
+    round(negative, x.m as u128 * y.m as u128, x.e + y.e, false)
+}
+
+pub fn div(a: f64, b: f64) -> f64 {
+    let (a, b) = (a.to_bits(), b.to_bits());
+    let negative = (a ^ b) & SIGN != 0;
+
+    if is_nan(a) || is_nan(b) {
+        return propagate_nan(a, b);
+    }
+    if is_inf(a) {
+        if is_inf(b) {
+            return f64::from_bits(DEFAULT_NAN);
+        }
+        return signed(negative, EXP_MASK);
+    }
+    if is_inf(b) {
+        return signed(negative, 0);
+    }
+    if is_zero(b) {
+        if is_zero(a) {
+            return f64::from_bits(DEFAULT_NAN);
+        }
+        return signed(negative, EXP_MASK);
+    }
+    if is_zero(a) {
+        return signed(negative, 0);
+    }
+
+    let (x, y) = (unpack(a), unpack(b));
+    let dividend = (x.m as u128) << 74;
+    let divisor = y.m as u128;
+
+    round(
+        negative,
+        dividend / divisor,
+        x.e - 74 - y.e,
+        !dividend.is_multiple_of(divisor),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIN_SUBNORMAL: f64 = 5e-324;
+
+    type BinaryOp = fn(f64, f64) -> f64;
+
+    // Every pair through add, sub, mul and div, bit for bit against the FPU. NaN payloads are
+    // implementation defined, so only NaN-ness is compared.
+    fn check(pairs: &[(f64, f64)]) {
+        let ops: [(&str, BinaryOp, BinaryOp); 4] = [
+            ("add", add, |a, b| a + b),
+            ("sub", sub, |a, b| a - b),
+            ("mul", mul, |a, b| a * b),
+            ("div", div, |a, b| a / b),
+        ];
+
+        for &(a, b) in pairs {
+            for (name, soft, hard) in ops {
+                let (soft, hard) = (soft(a, b), hard(a, b));
+
+                if hard.is_nan() {
+                    assert!(soft.is_nan(), "{}({:e}, {:e}) = {:e}", name, a, b, soft);
+                } else {
+                    assert_eq!(
+                        soft.to_bits(),
+                        hard.to_bits(),
+                        "{}({:e}, {:e}) = {:e}, expected {:e}",
+                        name,
+                        a,
+                        b,
+                        soft,
+                        hard
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn normal_operands() {
+        check(&[
+            (1.0, 3.0),
+            (0.1, 0.2),
+            (-2.5, 7.75),
+            (1e300, 1e-300),
+            (123456.789, -0.000321),
+            (1.0, -1.0),
+        ]);
+    }
+
+    #[test]
+    fn subnormal_operands_and_results() {
+        check(&[
+            (MIN_SUBNORMAL, MIN_SUBNORMAL),
+            (f64::MIN_POSITIVE, -MIN_SUBNORMAL),
+            (f64::MIN_POSITIVE / 3.0, 1.5),
+            (1e-310, 1.11),
+            (1e-160, 1e-160),
+            (f64::MIN_POSITIVE, 4503599627370496.0),
+            (1e-320, -1e-300),
+        ]);
+    }
+
+    #[test]
+    fn overflow_and_specials() {
+        check(&[
+            (f64::MAX, f64::MAX),
+            (f64::MAX, 1e-300),
+            (-f64::MAX, 2.0),
+            (f64::INFINITY, f64::NEG_INFINITY),
+            (f64::INFINITY, 0.0),
+            (0.0, 0.0),
+            (-0.0, -0.0),
+            (1.0, 0.0),
+            (f64::NAN, 1.0),
+        ]);
+    }
+
+    #[test]
+    fn ties_round_to_even() {
+        let ulp = f64::EPSILON;
+        check(&[
+            // exactly halfway between 1 and its successor, rounds down to the even 1
+            (1.0, ulp / 2.0),
+            // halfway between odd 1 + ulp and even 1 + 2 ulp, rounds up
+            (1.0 + ulp, ulp / 2.0),
+            (-1.0, -ulp / 2.0),
+            (2f64.powi(53), 1.0),
+            (2f64.powi(53) + 2.0, 1.0),
+            (1.0 + ulp, 1.0 + ulp),
+        ]);
+    }
+
+    #[test]
+    fn random_bit_patterns() {
+        // xorshift64, the same pairs every run
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            f64::from_bits(state)
+        };
+
+        let pairs: Vec<(f64, f64)> = (0..100_000).map(|_| (next(), next())).collect();
+        check(&pairs);
+
+        // exponents within 64 of each other, so sums cancel and round in every position
+        let mut near = || {
+            let bits = next().to_bits();
+            let exponent = 1023 - 32 + (bits >> 52) % 64;
+            f64::from_bits(bits & 0x800f_ffff_ffff_ffff | exponent << 52)
+        };
+        let pairs: Vec<(f64, f64)> = (0..100_000).map(|_| (near(), near())).collect();
+        check(&pairs);
+    }
+}
@@ -0,0 +1,149 @@
+use std::hint::black_box;
+
+use crate::SAMPLE_SIZE;
+use crate::error::{FingerprintError, Result};
+
+use crate::math::{Math, MathLibrary, PortableMath, SystemMath};
+use crate::softfloat::{Arithmetic, Float, SoftF64};
+
+use super::FingerprintTest;
+
+/// Iterates values near and below the subnormal threshold, mixed with libm sin/cos/atan.
+pub struct EnhancedDenormalTest {
+    pub sample_size: usize,
+    pub math: MathLibrary,
+    pub arithmetic: Arithmetic,
+}
+
+impl Default for EnhancedDenormalTest {
+    fn default() -> Self {
+        Self {
+            sample_size: SAMPLE_SIZE,
+            math: MathLibrary::System,
+            arithmetic: Arithmetic::Hardware,
+        }
+    }
+}
+
+impl FingerprintTest for EnhancedDenormalTest {
+    fn id(&self) -> &'static str {
+        "denormal"
+    }
+
+    fn name(&self) -> &'static str {
+        "Enhanced Denormal Numbers Test"
+    }
+
+    fn description(&self) -> &'static str {
+        "Repeated division and multiplication of subnormal and near-subnormal values"
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn sample_size(&self) -> Option<usize> {
+        Some(self.sample_size)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.sample_size < STARTING_VALUES.len() {
+            return Err(FingerprintError::InvalidConfiguration(format!(
+                "the denormal test needs a sample size of at least {}",
+                STARTING_VALUES.len()
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn run(&self) -> Result<Vec<f64>> {
+        self.validate()?;
+
+        let size = self.sample_size;
+        Ok(match (self.math, self.arithmetic) {
+            (MathLibrary::System, Arithmetic::Hardware) => {
+                enhanced_denormal_test::<SystemMath, f64>(size)
+            }
+            (MathLibrary::System, Arithmetic::SoftFloat) => {
+                enhanced_denormal_test::<SystemMath, SoftF64>(size)
+            }
+            (MathLibrary::Portable, Arithmetic::Hardware) => {
+                enhanced_denormal_test::<PortableMath, f64>(size)
+            }
+            (MathLibrary::Portable, Arithmetic::SoftFloat) => {
+                enhanced_denormal_test::<PortableMath, SoftF64>(size)
+            }
+        })
+    }
+
+    fn with_math(&self, math: MathLibrary) -> Option<Box<dyn FingerprintTest>> {
+        Some(Box::new(Self { math, ..*self }))
+    }
+
+    fn with_arithmetic(&self, arithmetic: Arithmetic) -> Option<Box<dyn FingerprintTest>> {
+        Some(Box::new(Self {
+            arithmetic,
+            ..*self
+        }))
+    }
+
+    fn describe_result(&self, index: usize) -> Option<String> {
+        let per_start = self.sample_size / STARTING_VALUES.len();
+        let start = STARTING_VALUES.get(index.checked_div(per_start)?)?;
+
+        Some(format!(
+            "start {:e}, iteration {}",
+            start,
+            index % per_start
+        ))
+    }
+}
+
+// The `x` sequence is x / X_DIVISOR + x * X_FACTOR
+pub(super) const X_DIVISOR: f64 = 1.1123156;
+pub(super) const X_FACTOR: f64 = 0.9123545676;
+
+/// One step of the `x` sequence of [`enhanced_denormal_test`], with the constants passed in so
+/// callers decide how they are kept from constant folding.
+#[inline(always)]
+pub fn denormal_step<T: Float>(x: T, divisor: T, factor: T) -> T {
+    x / divisor + x * factor
+}
+
+const STARTING_VALUES: [f64; 6] = [
+    1e-308,
+    2e-308,
+    5e-308,
+    1e-307,
+    1e-320,
+    2.2250738585072014e-308,
+];
+
+// With lower sample sizes this will not be unique
+pub fn enhanced_denormal_test<M: Math, T: Float>(sample_size: usize) -> Vec<f64> {
+    // every literal goes through black_box so nothing is constant folded at build time
+    let c = |val: f64| T::from_f64(black_box(val));
+    let mut results = Vec::with_capacity(sample_size);
+
+    for &start in STARTING_VALUES.iter() {
+        let mut x = c(start);
+        let mut y = c(start) * c(1.112345);
+
+        for i in 0..sample_size / STARTING_VALUES.len() {
+            x = denormal_step(x, c(X_DIVISOR), c(X_FACTOR));
+            y = y * c(0.951235467) + y / c(1.05123245);
+
+            let step = (c(i as f64) * c(0.01)).to_f64();
+            let combined = x * (c(1.0) + c(M::sin(step))) + y * (c(1.0) + c(M::cos(step)));
+
+            let final_val = combined
+                + c(M::sin((combined * c(1e300)).to_f64())) * c(1e-308)
+                + c(M::atan((combined * c(1e200)).to_f64())) * c(1e-308);
+
+            results.push(final_val.to_f64());
+        }
+    }
+
+    results
+}
@@ -0,0 +1,176 @@
+use std::hint::black_box;
+
+use crate::SAMPLE_SIZE;
+use crate::error::{FingerprintError, Result};
+
+use super::FingerprintTest;
+
+/// Evaluates polynomial and dot product kernels with fused and with separate multiply-add.
+pub struct FmaTest {
+    pub sample_size: usize,
+}
+
+impl Default for FmaTest {
+    fn default() -> Self {
+        Self {
+            sample_size: SAMPLE_SIZE,
+        }
+    }
+}
+
+/// The variants in the order their results are recorded, each a block of a polynomial and a dot
+/// product result per input. `_mm_fmadd_pd` is only recorded when the CPU has FMA.
+pub const VARIANTS: [&str; 3] = ["mul_add", "separate", "_mm_fmadd_pd"];
+
+const KERNELS: usize = 2;
+
+// Taylor series of sin, highest degree first
+const POLYNOMIAL: [f64; 8] = [
+    -1.0 / 1_307_674_368_000.0,
+    1.0 / 6_227_020_800.0,
+    -1.0 / 39_916_800.0,
+    1.0 / 362_880.0,
+    -1.0 / 5040.0,
+    1.0 / 120.0,
+    -1.0 / 6.0,
+    1.0,
+];
+
+const DOT_LENGTH: usize = 16;
+
+impl FingerprintTest for FmaTest {
+    fn id(&self) -> &'static str {
+        "fma"
+    }
+
+    fn name(&self) -> &'static str {
+        "Fused Multiply-Add Test"
+    }
+
+    fn description(&self) -> &'static str {
+        "Polynomial and dot product kernels with mul_add, _mm_fmadd_pd and separate mul+add"
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn sample_size(&self) -> Option<usize> {
+        Some(self.sample_size)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.sample_size == 0 {
+            return Err(FingerprintError::InvalidConfiguration(
+                "the fma test needs a sample size of at least 1".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn run(&self) -> Result<Vec<f64>> {
+        self.validate()?;
+
+        let inputs = black_box(test_inputs(self.sample_size));
+
+        let mut results = kernels(&inputs, f64::mul_add);
+        results.extend(kernels(&inputs, |a, b, c| a * b + c));
+
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("fma") {
+            // SAFETY: FMA was detected above
+            results.extend(kernels(&inputs, |a, b, c| unsafe { fmadd(a, b, c) }));
+        }
+
+        Ok(results)
+    }
+
+    fn describe_result(&self, index: usize) -> Option<String> {
+        let per_variant = self.sample_size * KERNELS;
+        let variant = VARIANTS.get(index.checked_div(per_variant)?)?;
+        let input = test_inputs(self.sample_size)[index % per_variant / KERNELS];
+        let kernel = if index.is_multiple_of(KERNELS) {
+            "polynomial"
+        } else {
+            "dot product"
+        };
+
+        Some(format!("{} {} at x = {:e}", variant, kernel, input))
+    }
+
+    fn sub_results(&self, results: &[f64]) -> Vec<(String, Vec<f64>)> {
+        VARIANTS
+            .iter()
+            .zip(results.chunks(self.sample_size * KERNELS))
+            .map(|(name, block)| (name.to_string(), block.to_vec()))
+            .collect()
+    }
+
+    fn notes(&self) -> Vec<String> {
+        // without the target feature the call goes through libm, which decides at runtime
+        let mul_add = if cfg!(target_feature = "fma") {
+            "mul_add compiles to a hardware FMA instruction"
+        } else if fma_detected() {
+            "mul_add calls libm fma() (not compiled with target feature fma), which may use the CPU's FMA instruction, glibc picks it at runtime"
+        } else {
+            "mul_add calls libm fma() (not compiled with target feature fma), which rounds in software since the CPU has no FMA"
+        };
+
+        let intrinsic = if cfg!(target_arch = "x86_64") && fma_detected() {
+            "_mm_fmadd_pd runs on this CPU"
+        } else {
+            "_mm_fmadd_pd is unavailable, the CPU has no FMA"
+        };
+
+        vec![mul_add.to_string(), intrinsic.to_string()]
+    }
+}
+
+/// Evenly spaced inputs in [-2, 2).
+pub fn test_inputs(sample_size: usize) -> Vec<f64> {
+    (0..sample_size)
+        .map(|i| -2.0 + 4.0 * i as f64 / sample_size as f64)
+        .collect()
+}
+
+// A Horner polynomial and a dot product over the next DOT_LENGTH inputs for every input,
+// with every multiply-add done by `madd(a, b, c) = a * b + c`
+fn kernels(inputs: &[f64], madd: impl Fn(f64, f64, f64) -> f64) -> Vec<f64> {
+    let mut results = Vec::with_capacity(inputs.len() * KERNELS);
+
+    for (i, &x) in inputs.iter().enumerate() {
+        let x2 = x * x;
+        let polynomial = POLYNOMIAL
+            .iter()
+            .fold(0.0, |acc, &coefficient| madd(acc, x2, coefficient))
+            * x;
+
+        let dot = (0..DOT_LENGTH).fold(0.0, |acc, j| {
+            let a = inputs[(i + j) % inputs.len()];
+            let b = POLYNOMIAL[j % POLYNOMIAL.len()] * (j as f64 + 0.1);
+            madd(a, b, acc)
+        });
+
+        results.push(polynomial);
+        results.push(dot);
+    }
+
+    results
+}
+
+fn fma_detected() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    return is_x86_feature_detected!("fma");
+
+    #[cfg(not(target_arch = "x86_64"))]
+    return false;
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "fma")]
+fn fmadd(a: f64, b: f64, c: f64) -> f64 {
+    use std::arch::x86_64::*;
+
+    _mm_cvtsd_f64(_mm_fmadd_pd(_mm_set_sd(a), _mm_set_sd(b), _mm_set_sd(c)))
+}
@@ -0,0 +1,480 @@
+use std::hint::spin_loop;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Barrier, Mutex};
+use std::thread::{self, ScopedJoinHandle};
+use std::time::Instant;
+
+use crate::affinity::{allowed_cpus, pin_current_thread};
+use crate::error::{FingerprintError, Result};
+
+use super::{FingerprintTest, ResultKind};
+
+/// Bounces a cache line between every pair of allowed CPUs and clusters the latencies into
+/// tiers.
+///
+/// The tiers follow the physical layout, e.g. SMT siblings, cores sharing an L3, chiplets and
+/// sockets, so they tell apart machines whose float results are identical. The result is the
+/// tier of every pair, not the latencies themselves, so frequency scaling and noise don't change
+/// the fingerprint as long as the tiers stay apart.
+///
+/// The results are the number of tiers, the number of CPUs and the CPUs themselves, then the tier
+/// of every pair `i < j`, row by row, so a recorded run names its pairs.
+pub struct CoreLatencyTest {
+    /// Round trips per timed trial.
+    pub round_trips: usize,
+    pub trials: usize,
+    last: Mutex<Option<LatencyMatrix>>,
+}
+
+pub const ROUND_TRIPS: usize = 1000;
+
+pub const TRIALS: usize = 7;
+
+/// A latency counts as the next tier when it is this much above the previous one.
+pub const TIER_GAP: f64 = 1.25;
+
+// Differences below this are noise however small the latencies are
+const MIN_TIER_STEP_NS: f64 = 5.0;
+
+#[repr(align(64))]
+struct CacheLine(AtomicU64);
+
+/// One-way latencies between every pair of CPUs, in nanoseconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyMatrix {
+    pub cpus: Vec<usize>,
+    /// `latencies[i][j]` between `cpus[i]` and `cpus[j]`, zero on the diagonal.
+    pub latencies: Vec<Vec<f64>>,
+}
+
+/// Clustered [`LatencyMatrix`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LatencyTiers {
+    /// Mean latency of each tier, fastest first.
+    pub means: Vec<f64>,
+    /// Tier of every pair `i < j`, row by row.
+    pub pairs: Vec<usize>,
+}
+
+impl CoreLatencyTest {
+    pub fn new(round_trips: usize, trials: usize) -> Self {
+        Self {
+            round_trips,
+            trials,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// The matrix of the most recent run.
+    pub fn last_matrix(&self) -> Option<LatencyMatrix> {
+        self.last.lock().unwrap().clone()
+    }
+}
+
+impl Default for CoreLatencyTest {
+    fn default() -> Self {
+        Self::new(ROUND_TRIPS, TRIALS)
+    }
+}
+
+impl FingerprintTest for CoreLatencyTest {
+    fn id(&self) -> &'static str {
+        "core-latency"
+    }
+
+    fn name(&self) -> &'static str {
+        "Core-to-Core Latency Test"
+    }
+
+    fn description(&self) -> &'static str {
+        "Cache line round trips between every pair of CPUs, clustered into latency tiers"
+    }
+
+    fn version(&self) -> u32 {
+        2
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.round_trips == 0 || self.trials == 0 {
+            return Err(FingerprintError::InvalidConfiguration(
+                "the core latency test needs at least 1 round trip and trial".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn run(&self) -> Result<Vec<f64>> {
+        self.validate()?;
+
+        let cpus =
+            allowed_cpus().map_err(|source| FingerprintError::Affinity { cpu: None, source })?;
+        let matrix = measure_matrix(&cpus, self.round_trips, self.trials)?;
+        let tiers = cluster(&matrix);
+        *self.last.lock().unwrap() = Some(matrix);
+
+        let mut results = vec![tiers.means.len() as f64, cpus.len() as f64];
+        results.extend(cpus.iter().map(|&cpu| cpu as f64));
+        results.extend(tiers.pairs.iter().map(|&tier| tier as f64));
+
+        Ok(results)
+    }
+
+    fn describe_result(&self, index: usize) -> Option<String> {
+        let cpus = self
+            .last_matrix()
+            .map_or_else(Vec::new, |matrix| matrix.cpus);
+        describe(&cpus, index)
+    }
+
+    fn describe_recorded_result(&self, results: &[f64], index: usize) -> Option<String> {
+        let count = *results.get(1)? as usize;
+        let cpus: Vec<usize> = results
+            .get(2..2 + count)?
+            .iter()
+            .map(|&cpu| cpu as usize)
+            .collect();
+
+        describe(&cpus, index)
+    }
+
+    fn result_kind(&self, _index: usize) -> ResultKind {
+        ResultKind::Category
+    }
+
+    fn pins_threads(&self) -> bool {
+        true
+    }
+
+    fn notes(&self) -> Vec<String> {
+        let Some(matrix) = self.last_matrix() else {
+            return Vec::new();
+        };
+
+        if matrix.cpus.len() < 2 {
+            return vec!["only one CPU is available, there are no pairs to measure".to_string()];
+        }
+
+        let tiers = cluster(&matrix);
+        let mut notes: Vec<String> = tiers
+            .means
+            .iter()
+            .enumerate()
+            .map(|(tier, mean)| {
+                let count = tiers.pairs.iter().filter(|&&t| t == tier).count();
+                format!("tier {}: {:.1} ns one way, {} pairs", tier, mean, count)
+            })
+            .collect();
+
+        for (cpu, row) in matrix.cpus.iter().zip(matrix.latencies.iter()) {
+            let row: Vec<String> = row
+                .iter()
+                .map(|latency| format!("{:6.1}", latency))
+                .collect();
+            notes.push(format!("CPU {:3} ns: {}", cpu, row.join(" ")));
+        }
+
+        notes
+    }
+}
+
+/// Measures the one-way latency between every pair of `cpus`.
+pub fn measure_matrix(cpus: &[usize], round_trips: usize, trials: usize) -> Result<LatencyMatrix> {
+    let mut latencies = vec![vec![0.0; cpus.len()]; cpus.len()];
+
+    for i in 0..cpus.len() {
+        for j in i + 1..cpus.len() {
+            let latency = measure_pair(cpus[i], cpus[j], round_trips, trials)?;
+            latencies[i][j] = latency;
+            latencies[j][i] = latency;
+        }
+    }
+
+    Ok(LatencyMatrix {
+        cpus: cpus.to_vec(),
+        latencies,
+    })
+}
+
+/// Median one-way latency of `trials` runs of `round_trips` round trips between a thread pinned
+/// to `ping` and one pinned to `pong`, after one untimed run.
+pub fn measure_pair(ping: usize, pong: usize, round_trips: usize, trials: usize) -> Result<f64> {
+    let line = CacheLine(AtomicU64::new(0));
+    let barrier = Barrier::new(2);
+    let failed = AtomicBool::new(false);
+    let total = (trials + 1) * round_trips;
+
+    // both threads pin themselves before the barrier, so neither spins waiting for a thread
+    // that gave up
+    let pin = |cpu: usize| {
+        let pinned = pin_current_thread(cpu).map_err(|source| FingerprintError::Affinity {
+            cpu: Some(cpu),
+            source,
+        });
+        if pinned.is_err() {
+            failed.store(true, Ordering::SeqCst);
+        }
+        barrier.wait();
+
+        match pinned {
+            Ok(()) if failed.load(Ordering::SeqCst) => Ok(false),
+            Ok(()) => Ok(true),
+            Err(err) => Err(err),
+        }
+    };
+
+    thread::scope(|scope| {
+        let responder = scope.spawn(|| {
+            if !pin(pong)? {
+                return Ok(());
+            }
+
+            for round_trip in 0..total as u64 {
+                let value = 2 * round_trip + 1;
+                while line.0.load(Ordering::Acquire) != value {
+                    spin_loop();
+                }
+                line.0.store(value + 1, Ordering::Release);
+            }
+
+            Ok(())
+        });
+
+        let initiator = scope.spawn(|| {
+            if !pin(ping)? {
+                return Ok(Vec::new());
+            }
+
+            let mut latencies = Vec::with_capacity(trials);
+            let mut round_trip = 0u64;
+
+            for trial in 0..=trials {
+                let start = Instant::now();
+
+                for _ in 0..round_trips {
+                    let value = 2 * round_trip + 1;
+                    line.0.store(value, Ordering::Release);
+                    while line.0.load(Ordering::Acquire) != value + 1 {
+                        spin_loop();
+                    }
+                    round_trip += 1;
+                }
+
+                // the first trial only warms up
+                if trial > 0 {
+                    let nanos = start.elapsed().as_nanos() as f64;
+                    latencies.push(nanos / (2 * round_trips) as f64);
+                }
+            }
+
+            Ok(latencies)
+        });
+
+        let latencies = join(initiator);
+        let responded = join(responder);
+
+        let mut latencies = latencies?;
+        responded?;
+
+        Ok(median(&mut latencies))
+    })
+}
+
+fn join<T>(handle: ScopedJoinHandle<'_, T>) -> T {
+    handle
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+}
+
+/// Groups the latencies of all pairs into tiers: sorted, a new tier starts wherever a latency
+/// is more than [`TIER_GAP`] times the one before it.
+pub fn cluster(matrix: &LatencyMatrix) -> LatencyTiers {
+    let n = matrix.cpus.len();
+    let pairs: Vec<f64> = (0..n)
+        .flat_map(|i| (i + 1..n).map(move |j| (i, j)))
+        .map(|(i, j)| matrix.latencies[i][j])
+        .collect();
+
+    let mut sorted = pairs.clone();
+    sorted.sort_by(f64::total_cmp);
+
+    // lowest latency of each tier
+    let mut bounds: Vec<f64> = Vec::new();
+    let mut members: Vec<Vec<f64>> = Vec::new();
+    let mut previous = None;
+
+    for &latency in sorted.iter() {
+        let new_tier = previous.is_none_or(|previous: f64| {
+            latency > previous * TIER_GAP && latency - previous > MIN_TIER_STEP_NS
+        });
+
+        if new_tier {
+            bounds.push(latency);
+            members.push(Vec::new());
+        }
+        members.last_mut().unwrap().push(latency);
+        previous = Some(latency);
+    }
+
+    let means = members
+        .iter()
+        .map(|tier| tier.iter().sum::<f64>() / tier.len() as f64)
+        .collect();
+    let pairs = pairs
+        .iter()
+        .map(|&latency| bounds.iter().rposition(|&bound| latency >= bound).unwrap())
+        .collect();
+
+    LatencyTiers { means, pairs }
+}
+
+/// Describes the result at `index` of a run over `cpus`.
+fn describe(cpus: &[usize], index: usize) -> Option<String> {
+    match index {
+        0 => Some("number of latency tiers".to_string()),
+        1 => Some("number of CPUs".to_string()),
+        _ if index < 2 + cpus.len() => Some(format!("CPU number {}", index - 2)),
+        _ => {
+            let (i, j) = pair(cpus.len(), index - 2 - cpus.len())?;
+            Some(format!(
+                "latency tier of CPU {} and CPU {}",
+                cpus[i], cpus[j]
+            ))
+        }
+    }
+}
+
+/// Row and column of the `index`th pair `i < j` of `n` CPUs.
+fn pair(n: usize, index: usize) -> Option<(usize, usize)> {
+    let mut remaining = index;
+
+    for i in 0..n {
+        let row = n - i - 1;
+        if remaining < row {
+            return Some((i, i + 1 + remaining));
+        }
+        remaining -= row;
+    }
+
+    None
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(n: usize, latency: impl Fn(usize, usize) -> f64) -> LatencyMatrix {
+        LatencyMatrix {
+            cpus: (0..n).collect(),
+            latencies: (0..n)
+                .map(|i| {
+                    (0..n)
+                        .map(|j| {
+                            if i == j {
+                                0.0
+                            } else {
+                                latency(i.min(j), i.max(j))
+                            }
+                        })
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn sockets_cores_and_siblings_are_separate_tiers() {
+        // 2 sockets of 2 cores with 2 threads each, CPU = socket * 4 + core * 2 + thread
+        let tiers = cluster(&matrix(8, |i, j| {
+            if i / 4 != j / 4 {
+                120.0
+            } else if i / 2 != j / 2 {
+                40.0
+            } else {
+                8.0
+            }
+        }));
+
+        assert_eq!(tiers.means, [8.0, 40.0, 120.0]);
+        assert_eq!(tiers.pairs.len(), 28);
+        assert_eq!(tiers.pairs.iter().filter(|&&tier| tier == 0).count(), 4);
+        assert_eq!(tiers.pairs.iter().filter(|&&tier| tier == 1).count(), 8);
+        assert_eq!(tiers.pairs.iter().filter(|&&tier| tier == 2).count(), 16);
+    }
+
+    #[test]
+    fn noise_within_a_tier_doesnt_split_it() {
+        // every step is below TIER_GAP although the tier spans more than that
+        let tiers = cluster(&matrix(4, |i, j| [40.0, 44.0, 48.0, 53.0, 58.0][i + j - 1]));
+
+        assert_eq!(tiers.means.len(), 1);
+        assert!(tiers.pairs.iter().all(|&tier| tier == 0));
+    }
+
+    #[test]
+    fn noisy_values_near_a_tier_boundary() {
+        let latencies = |upper: f64| {
+            move |i: usize, j: usize| {
+                if i / 2 == j / 2 { 40.0 } else { upper }
+            }
+        };
+
+        // just under the gap, and just over it
+        let below = cluster(&matrix(4, latencies(40.0 * TIER_GAP - 0.5)));
+        let above = cluster(&matrix(4, latencies(40.0 * TIER_GAP + 0.5)));
+
+        assert_eq!(below.means.len(), 1);
+        assert_eq!(above.means.len(), 2);
+        assert_eq!(above.pairs, [0, 1, 1, 1, 1, 0]);
+    }
+
+    #[test]
+    fn small_steps_are_noise_even_above_the_gap() {
+        // 2 ns to 4 ns is twice the latency, but below MIN_TIER_STEP_NS
+        let tiers = cluster(&matrix(4, |i, j| if i / 2 == j / 2 { 2.0 } else { 4.0 }));
+
+        assert_eq!(tiers.means.len(), 1);
+    }
+
+    #[test]
+    fn one_cpu_has_no_tiers() {
+        let tiers = cluster(&matrix(1, |_, _| unreachable!()));
+
+        assert_eq!(
+            tiers,
+            LatencyTiers {
+                means: Vec::new(),
+                pairs: Vec::new()
+            }
+        );
+    }
+
+    #[test]
+    fn pairs_are_numbered_row_by_row() {
+        let pairs: Vec<_> = (0..7).map(|index| pair(4, index)).collect();
+
+        assert_eq!(
+            pairs,
+            [
+                Some((0, 1)),
+                Some((0, 2)),
+                Some((0, 3)),
+                Some((1, 2)),
+                Some((1, 3)),
+                Some((2, 3)),
+                None
+            ]
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! The fingerprint tests and the registry listing them.
+
+mod denormal;
+mod fma;
+#[cfg(target_os = "linux")]
+mod latency;
+mod nan;
+#[cfg(target_arch = "x86_64")]
+mod reciprocal;
+mod timing;
+mod transcendental;
+#[cfg(target_arch = "x86_64")]
+mod x87;
+
+use crate::error::{FingerprintError, Result};
+use crate::math::MathLibrary;
+use crate::softfloat::Arithmetic;
+
+pub use denormal::{EnhancedDenormalTest, enhanced_denormal_test};
+pub use fma::FmaTest;
+#[cfg(target_os = "linux")]
+pub use latency::CoreLatencyTest;
+pub use nan::NanPropagationTest;
+#[cfg(target_arch = "x86_64")]
+pub use reciprocal::ReciprocalEstimateTest;
+pub use timing::DenormalTimingTest;
+pub use transcendental::{TranscendentalFunctionTest, transcendental_function_test};
+#[cfg(target_arch = "x86_64")]
+pub use x87::X87Test;
+
+/// What a result value means, which decides how `compare` shows a difference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultKind {
+    /// A computed number, differences are measured in ulps.
+    Numeric,
+    /// A small integer naming a category, e.g. a bucket or tier, distances mean nothing.
+    Category,
+    /// A bit pattern stored in an `f64`, e.g. an x87 word or a NaN payload.
+    Bits,
+}
+
+/// A computation whose exact results depend on the hardware (or libm) it runs on.
+///
+/// Tests are shared with worker threads pinned to each CPU, see [`crate::per_core`].
+pub trait FingerprintTest: Send + Sync {
+    /// Short stable identifier, used on the command line and in encoded results.
+    fn id(&self) -> &'static str;
+
+    /// Human readable name, e.g. `"Transcendental Function Test"`.
+    fn name(&self) -> &'static str;
+
+    /// One line explanation of what the test exercises.
+    fn description(&self) -> &'static str;
+
+    /// Bumped whenever the computation changes, fingerprints of different versions can't be compared.
+    fn version(&self) -> u32;
+
+    /// Runs the test and returns the raw results in a fixed order.
+    fn run(&self) -> Result<Vec<f64>>;
+
+    /// Number of inputs taken from `--sample-size`, `None` for tests with fixed inputs.
+    fn sample_size(&self) -> Option<usize> {
+        None
+    }
+
+    /// Checks the configuration, so a bad value is rejected before any test runs. [`run`]
+    /// checks it too.
+    ///
+    /// [`run`]: FingerprintTest::run
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// The same test running on another math library, `None` if the test doesn't call libm.
+    fn with_math(&self, _math: MathLibrary) -> Option<Box<dyn FingerprintTest>> {
+        None
+    }
+
+    /// The same test with its basic operations done by `arithmetic`, `None` if the test has no
+    /// software reference.
+    fn with_arithmetic(&self, _arithmetic: Arithmetic) -> Option<Box<dyn FingerprintTest>> {
+        None
+    }
+
+    /// Describes the input that produced the result at `index`, if the test knows it.
+    fn describe_result(&self, _index: usize) -> Option<String> {
+        None
+    }
+
+    /// Like [`FingerprintTest::describe_result`], for the `results` of a run recorded earlier,
+    /// e.g. in a report being compared. Tests whose inputs depend on the machine record them in
+    /// the results and override this.
+    fn describe_recorded_result(&self, _results: &[f64], index: usize) -> Option<String> {
+        self.describe_result(index)
+    }
+
+    /// What the result at `index` means, [`ResultKind::Numeric`] unless the test says otherwise.
+    fn result_kind(&self, _index: usize) -> ResultKind {
+        ResultKind::Numeric
+    }
+
+    /// Named subsets of `results` that each get their own fingerprint.
+    ///
+    /// When this is not empty the test fingerprint is a hash of the sub-fingerprints, so a
+    /// difference can be traced back to the part that caused it.
+    fn sub_results(&self, _results: &[f64]) -> Vec<(String, Vec<f64>)> {
+        Vec::new()
+    }
+
+    /// Facts about how the test ran on this machine, e.g. which code path it took. Asked for
+    /// after a run, so it can describe that run.
+    fn notes(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// True when the test pins its own threads, so running it pinned to one CPU adds nothing.
+    /// [`crate::per_core`] and the core classes of [`crate::hybrid`] skip such tests.
+    fn pins_threads(&self) -> bool {
+        false
+    }
+
+    /// False when MXCSR doesn't govern the test's arithmetic, e.g. x87 instructions with their
+    /// own control word, or threads the test spawns itself. The rounding and subnormal sweeps of
+    /// [`crate::mxcsr`] skip such tests.
+    fn follows_mxcsr(&self) -> bool {
+        !self.pins_threads()
+    }
+}
+
+/// Every available test, in the order they are run.
+pub fn registry(sample_size: usize) -> Vec<Box<dyn FingerprintTest>> {
+    #[allow(unused_mut)]
+    let mut tests: Vec<Box<dyn FingerprintTest>> = vec![
+        Box::new(EnhancedDenormalTest {
+            sample_size,
+            math: MathLibrary::System,
+            arithmetic: Arithmetic::Hardware,
+        }),
+        Box::new(TranscendentalFunctionTest {
+            sample_size,
+            math: MathLibrary::System,
+        }),
+        Box::new(FmaTest { sample_size }),
+        Box::new(NanPropagationTest),
+    ];
+
+    #[cfg(target_arch = "x86_64")]
+    tests.push(Box::new(ReciprocalEstimateTest { sample_size }));
+    #[cfg(target_arch = "x86_64")]
+    tests.push(Box::new(X87Test));
+
+    tests
+}
+
+/// Tests that measure time instead of computing exact results, left out of [`registry`] and only
+/// run when selected by id.
+pub fn opt_in_tests(sample_size: usize) -> Vec<Box<dyn FingerprintTest>> {
+    #[allow(unused_mut)]
+    let mut tests: Vec<Box<dyn FingerprintTest>> = vec![Box::new(DenormalTimingTest::new(
+        sample_size,
+        timing::TRIALS,
+    ))];
+
+    #[cfg(target_os = "linux")]
+    tests.push(Box::new(CoreLatencyTest::new(
+        latency::ROUND_TRIPS,
+        latency::TRIALS,
+    )));
+
+    tests
+}
+
+/// Looks up a registered or opt-in test by its id.
+pub fn find_test(id: &str, sample_size: usize) -> Result<Box<dyn FingerprintTest>> {
+    registry(sample_size)
+        .into_iter()
+        .chain(opt_in_tests(sample_size))
+        .find(|test| test.id() == id)
+        .ok_or_else(|| FingerprintError::UnknownTest(id.to_string()))
+}
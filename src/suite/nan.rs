@@ -0,0 +1,208 @@
+use std::hint::black_box;
+
+use crate::error::Result;
+
+use super::{FingerprintTest, ResultKind};
+
+/// Pushes NaNs with distinct payloads and signs through arithmetic and records the output bits.
+///
+/// IEEE-754 leaves open which payload survives, whether the sign is kept and what the default
+/// NaN looks like, so these differ between architectures.
+pub struct NanPropagationTest;
+
+/// Quiet and signaling NaNs of both signs with distinct payloads.
+pub const NAN_INPUTS: [u64; 5] = [
+    0x7ff8_0000_0000_0001,
+    0xfff8_0000_0000_1234,
+    0x7ff0_0000_0000_0005,
+    0xfff4_0000_dead_beef,
+    0x7ffc_0000_0000_00ff,
+];
+
+// The non-NaN operand each NaN is also combined with, on both sides
+const OTHER: f64 = 1.0;
+
+type Binary = fn(f64, f64) -> f64;
+type Unary = fn(f64) -> f64;
+
+struct Case {
+    operation: &'static str,
+    operands: String,
+    result: f64,
+}
+
+impl FingerprintTest for NanPropagationTest {
+    fn id(&self) -> &'static str {
+        "nan"
+    }
+
+    fn name(&self) -> &'static str {
+        "NaN Propagation Test"
+    }
+
+    fn description(&self) -> &'static str {
+        "NaN payload and sign propagation through arithmetic, sqrt, min/max, conversions and SIMD"
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn run(&self) -> Result<Vec<f64>> {
+        Ok(cases().into_iter().map(|case| case.result).collect())
+    }
+
+    fn describe_result(&self, index: usize) -> Option<String> {
+        let case = cases().into_iter().nth(index)?;
+        Some(format!("{}({})", case.operation, case.operands))
+    }
+
+    fn result_kind(&self, _index: usize) -> ResultKind {
+        ResultKind::Bits
+    }
+
+    fn sub_results(&self, results: &[f64]) -> Vec<(String, Vec<f64>)> {
+        let mut parts: Vec<(String, Vec<f64>)> = Vec::new();
+
+        for (case, &result) in cases().iter().zip(results) {
+            match parts.last_mut() {
+                Some((name, values)) if name == case.operation => values.push(result),
+                _ => parts.push((case.operation.to_string(), vec![result])),
+            }
+        }
+
+        parts
+    }
+}
+
+fn binary_operations() -> Vec<(&'static str, Binary)> {
+    #[allow(unused_mut)]
+    let mut operations: Vec<(&'static str, Binary)> = vec![
+        ("add", |a, b| a + b),
+        ("sub", |a, b| a - b),
+        ("mul", |a, b| a * b),
+        ("div", |a, b| a / b),
+        ("min", f64::min),
+        ("max", f64::max),
+    ];
+
+    #[cfg(target_arch = "x86_64")]
+    operations.extend([
+        ("addpd", simd::add as Binary),
+        ("mulpd", simd::mul),
+        ("minpd", simd::min),
+        ("maxpd", simd::max),
+    ]);
+
+    operations
+}
+
+fn unary_operations() -> Vec<(&'static str, Unary)> {
+    vec![
+        ("sqrt", f64::sqrt),
+        ("neg", |a| -a),
+        ("abs", f64::abs),
+        ("f64 -> f32 -> f64", |a| a as f32 as f64),
+        ("mul_add(x, 1, 1)", |a| a.mul_add(1.0, 1.0)),
+    ]
+}
+
+fn operand(val: f64) -> String {
+    if val.is_nan() {
+        format!("{:016x}", val.to_bits())
+    } else {
+        format!("{:?}", val)
+    }
+}
+
+fn cases() -> Vec<Case> {
+    let nans: Vec<f64> = NAN_INPUTS
+        .iter()
+        .map(|&bits| black_box(f64::from_bits(bits)))
+        .collect();
+    let other = black_box(OTHER);
+    let mut cases = Vec::new();
+
+    for (operation, op) in binary_operations() {
+        let pairs = nans
+            .iter()
+            .flat_map(|&a| nans.iter().map(move |&b| (a, b)))
+            .chain(nans.iter().map(|&a| (a, other)))
+            .chain(nans.iter().map(|&b| (other, b)));
+
+        for (a, b) in pairs {
+            cases.push(Case {
+                operation,
+                operands: format!("{}, {}", operand(a), operand(b)),
+                result: op(a, b),
+            });
+        }
+    }
+
+    for (operation, op) in unary_operations() {
+        for &a in nans.iter() {
+            cases.push(Case {
+                operation,
+                operands: operand(a),
+                result: op(a),
+            });
+        }
+    }
+
+    // f32 NaNs widened to f64
+    for &a in nans.iter() {
+        let narrow = black_box(f32::from_bits((a.to_bits() >> 32) as u32 | 0x7f80_0000));
+        cases.push(Case {
+            operation: "f32 -> f64",
+            operands: format!("{:08x}", narrow.to_bits()),
+            result: narrow as f64,
+        });
+    }
+
+    // operations that create a NaN from non-NaN operands
+    let zero = black_box(0.0f64);
+    let inf = black_box(f64::INFINITY);
+    let defaults: [(&str, f64); 4] = [
+        ("0 / 0", zero / black_box(0.0)),
+        ("inf - inf", inf - black_box(f64::INFINITY)),
+        ("inf * 0", inf * zero),
+        ("sqrt(-1)", (zero - 1.0).sqrt()),
+    ];
+
+    for (operands, result) in defaults {
+        cases.push(Case {
+            operation: "default",
+            operands: operands.to_string(),
+            result,
+        });
+    }
+
+    cases
+}
+
+#[cfg(target_arch = "x86_64")]
+mod simd {
+    use std::arch::x86_64::*;
+
+    // The NaN goes in the low lane, the high lane holds ordinary numbers.
+    // SAFETY (for every intrinsic here): SSE2 is part of the x86_64 baseline
+    fn lanes(a: f64, b: f64, op: fn(__m128d, __m128d) -> __m128d) -> f64 {
+        unsafe { _mm_cvtsd_f64(op(_mm_set_pd(2.0, a), _mm_set_pd(3.0, b))) }
+    }
+
+    pub fn add(a: f64, b: f64) -> f64 {
+        lanes(a, b, |x, y| unsafe { _mm_add_pd(x, y) })
+    }
+
+    pub fn mul(a: f64, b: f64) -> f64 {
+        lanes(a, b, |x, y| unsafe { _mm_mul_pd(x, y) })
+    }
+
+    pub fn min(a: f64, b: f64) -> f64 {
+        lanes(a, b, |x, y| unsafe { _mm_min_pd(x, y) })
+    }
+
+    pub fn max(a: f64, b: f64) -> f64 {
+        lanes(a, b, |x, y| unsafe { _mm_max_pd(x, y) })
+    }
+}
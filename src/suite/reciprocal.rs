@@ -0,0 +1,173 @@
+use std::arch::x86_64::*;
+use std::hint::black_box;
+
+use crate::SAMPLE_SIZE;
+use crate::error::{FingerprintError, Result};
+
+use super::FingerprintTest;
+
+/// Sweeps f32 inputs through the hardware reciprocal and reciprocal square root estimates.
+///
+/// The estimates are only specified to a relative error bound, the exact bits are up to the
+/// microarchitecture.
+pub struct ReciprocalEstimateTest {
+    pub sample_size: usize,
+}
+
+impl Default for ReciprocalEstimateTest {
+    fn default() -> Self {
+        Self {
+            sample_size: SAMPLE_SIZE,
+        }
+    }
+}
+
+/// The instructions in the order their results are recorded, each a block of `sample_size`
+/// results. The AVX-512 ones are only recorded when the CPU has AVX-512F.
+pub const INSTRUCTIONS: [&str; 4] = ["rcpps", "rsqrtps", "vrcp14ps", "vrsqrt14ps"];
+
+impl FingerprintTest for ReciprocalEstimateTest {
+    fn id(&self) -> &'static str {
+        "reciprocal"
+    }
+
+    fn name(&self) -> &'static str {
+        "SIMD Reciprocal Estimate Test"
+    }
+
+    fn description(&self) -> &'static str {
+        "RCPPS/RSQRTPS (and RCP14/RSQRT14 with AVX-512) approximations over fixed f32 inputs"
+    }
+
+    fn version(&self) -> u32 {
+        2
+    }
+
+    fn sample_size(&self) -> Option<usize> {
+        Some(self.sample_size)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.sample_size == 0 {
+            return Err(FingerprintError::InvalidConfiguration(
+                "the reciprocal test needs a sample size of at least 1".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn run(&self) -> Result<Vec<f64>> {
+        if !is_x86_feature_detected!("sse") {
+            return Err(FingerprintError::UnsupportedCpuFeature {
+                test: self.id().to_string(),
+                feature: "sse".to_string(),
+            });
+        }
+
+        self.validate()?;
+
+        let inputs = black_box(test_inputs(self.sample_size));
+        let rsqrt_inputs = black_box(rsqrt_inputs(self.sample_size));
+        let mut results = Vec::with_capacity(inputs.len() * INSTRUCTIONS.len());
+
+        results.extend(map_sse(&inputs, |x| unsafe { _mm_rcp_ps(x) }));
+        results.extend(map_sse(&rsqrt_inputs, |x| unsafe { _mm_rsqrt_ps(x) }));
+
+        if is_x86_feature_detected!("avx512f") {
+            // SAFETY: AVX-512F was detected above
+            unsafe {
+                results.extend(rcp14(&inputs));
+                results.extend(rsqrt14(&rsqrt_inputs));
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn describe_result(&self, index: usize) -> Option<String> {
+        let instruction = INSTRUCTIONS.get(index.checked_div(self.sample_size)?)?;
+        let inputs = if instruction.contains("rsqrt") {
+            rsqrt_inputs(self.sample_size)
+        } else {
+            test_inputs(self.sample_size)
+        };
+        let input = inputs[index % self.sample_size];
+
+        Some(format!("{}({:e})", instruction, input))
+    }
+
+    fn sub_results(&self, results: &[f64]) -> Vec<(String, Vec<f64>)> {
+        INSTRUCTIONS
+            .iter()
+            .zip(results.chunks(self.sample_size))
+            .map(|(name, block)| (name.to_string(), block.to_vec()))
+            .collect()
+    }
+}
+
+/// The f32 inputs, spread over the whole mantissa range and over even and odd exponents (which
+/// take different table halves in most rsqrt implementations).
+pub fn test_inputs(sample_size: usize) -> Vec<f32> {
+    (0..sample_size as u32)
+        .map(|i| {
+            let mantissa = i.wrapping_mul(0x9e37_79b9) >> 9;
+            let exponent = 125 + i % 4;
+            let sign = (i % 8 / 4) << 31;
+            f32::from_bits(sign | exponent << 23 | mantissa)
+        })
+        .collect()
+}
+
+/// [`test_inputs`] without their sign, a negative input would only give the default NaN.
+pub fn rsqrt_inputs(sample_size: usize) -> Vec<f32> {
+    test_inputs(sample_size).into_iter().map(f32::abs).collect()
+}
+
+fn map_sse(inputs: &[f32], op: impl Fn(__m128) -> __m128) -> Vec<f64> {
+    let mut results = Vec::with_capacity(inputs.len());
+
+    for chunk in inputs.chunks(4) {
+        let mut lanes = [1.0f32; 4];
+        lanes[..chunk.len()].copy_from_slice(chunk);
+
+        // SAFETY: both pointers are to 4 f32s
+        let out = unsafe {
+            let mut out = [0.0f32; 4];
+            _mm_storeu_ps(out.as_mut_ptr(), op(_mm_loadu_ps(lanes.as_ptr())));
+            out
+        };
+
+        results.extend(out[..chunk.len()].iter().map(|&val| val as f64));
+    }
+
+    results
+}
+
+#[target_feature(enable = "avx512f")]
+unsafe fn rcp14(inputs: &[f32]) -> Vec<f64> {
+    unsafe { map_avx512(inputs, |x| _mm512_rcp14_ps(x)) }
+}
+
+#[target_feature(enable = "avx512f")]
+unsafe fn rsqrt14(inputs: &[f32]) -> Vec<f64> {
+    unsafe { map_avx512(inputs, |x| _mm512_rsqrt14_ps(x)) }
+}
+
+#[target_feature(enable = "avx512f")]
+unsafe fn map_avx512(inputs: &[f32], op: impl Fn(__m512) -> __m512) -> Vec<f64> {
+    let mut results = Vec::with_capacity(inputs.len());
+
+    for chunk in inputs.chunks(16) {
+        let mut lanes = [1.0f32; 16];
+        lanes[..chunk.len()].copy_from_slice(chunk);
+
+        let mut out = [0.0f32; 16];
+        // SAFETY: both pointers are to 16 f32s
+        unsafe { _mm512_storeu_ps(out.as_mut_ptr(), op(_mm512_loadu_ps(lanes.as_ptr()))) };
+
+        results.extend(out[..chunk.len()].iter().map(|&val| val as f64));
+    }
+
+    results
+}
@@ -0,0 +1,338 @@
+use std::hint::black_box;
+use std::sync::Mutex;
+
+use crate::SAMPLE_SIZE;
+use crate::error::{FingerprintError, Result};
+
+#[cfg(not(target_arch = "x86_64"))]
+use super::denormal::denormal_step;
+use super::denormal::{X_DIVISOR, X_FACTOR};
+use super::{FingerprintTest, ResultKind};
+
+/// Times the denormal test's `x` step on normal and on subnormal operands.
+///
+/// How much slower subnormals are depends on the microarchitecture: microcode assists cost over
+/// a hundred cycles on many Intel cores and far less on recent AMD ones. The result is the
+/// octave of the slowdown ratio, coarse enough to be stable from run to run. On x86_64 the timed
+/// loop is assembly, so debug and release builds measure the same instructions; elsewhere it is
+/// compiled Rust and only builds of the same profile compare.
+///
+/// Timing is noisy, so the test is not in [`super::registry`] and only runs when selected.
+pub struct DenormalTimingTest {
+    /// Operands per pass.
+    pub sample_size: usize,
+    pub trials: usize,
+    last: Mutex<Option<PenaltyMeasurement>>,
+}
+
+/// Bucket `n` holds slowdown ratios in `[2^n, 2^(n+1))`, ratios below 2x are bucket 0 and ratios
+/// of `2^MAX_BUCKET` and above are the last bucket.
+pub const MAX_BUCKET: usize = 8;
+
+pub const TRIALS: usize = 31;
+
+// Passes over the operands per timed trial
+const PASSES: usize = 20;
+
+// Divisions, multiplications and additions per step
+const OPS_PER_STEP: usize = 3;
+
+// Moves subnormal operands well into the normal range without changing their significand
+const NORMAL_SCALE: f64 = 4.149515568880993e180; // 2^600
+
+#[cfg(target_arch = "x86_64")]
+const TICK_UNIT: &str = "TSC ticks";
+#[cfg(not(target_arch = "x86_64"))]
+const TICK_UNIT: &str = "ns";
+
+/// Robust statistics over the trials of one timing run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PenaltyMeasurement {
+    /// Median cost of an operation on normal operands, in [`TICK_UNIT`]s.
+    pub normal_per_op: f64,
+    /// Median cost of an operation on subnormal operands.
+    pub subnormal_per_op: f64,
+    /// Median of the per-trial subnormal / normal ratios.
+    pub ratio: f64,
+    /// Median absolute deviation of the ratios.
+    pub ratio_mad: f64,
+    pub bucket: usize,
+}
+
+impl DenormalTimingTest {
+    pub fn new(sample_size: usize, trials: usize) -> Self {
+        Self {
+            sample_size,
+            trials,
+            last: Mutex::new(None),
+        }
+    }
+
+    /// The measurement of the most recent run.
+    pub fn last_measurement(&self) -> Option<PenaltyMeasurement> {
+        *self.last.lock().unwrap()
+    }
+}
+
+impl Default for DenormalTimingTest {
+    fn default() -> Self {
+        Self::new(SAMPLE_SIZE, TRIALS)
+    }
+}
+
+impl FingerprintTest for DenormalTimingTest {
+    fn id(&self) -> &'static str {
+        "denormal-timing"
+    }
+
+    fn name(&self) -> &'static str {
+        "Denormal Timing Penalty Test"
+    }
+
+    fn description(&self) -> &'static str {
+        "Slowdown of the denormal step on subnormal vs normal operands, bucketed"
+    }
+
+    fn version(&self) -> u32 {
+        2
+    }
+
+    fn sample_size(&self) -> Option<usize> {
+        Some(self.sample_size)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.sample_size == 0 || self.trials == 0 {
+            return Err(FingerprintError::InvalidConfiguration(
+                "the denormal timing test needs a sample size and trial count of at least 1"
+                    .to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn run(&self) -> Result<Vec<f64>> {
+        self.validate()?;
+
+        // every run is bucketed on its own, the same instance also serves the sweeps and every
+        // CPU of --per-core
+        let measurement = measure_penalty(self.sample_size, self.trials);
+        *self.last.lock().unwrap() = Some(measurement);
+
+        Ok(vec![measurement.bucket as f64])
+    }
+
+    fn describe_result(&self, index: usize) -> Option<String> {
+        (index == 0).then(|| "subnormal / normal slowdown bucket".to_string())
+    }
+
+    fn result_kind(&self, _index: usize) -> ResultKind {
+        ResultKind::Category
+    }
+
+    fn notes(&self) -> Vec<String> {
+        let Some(measurement) = self.last_measurement() else {
+            return Vec::new();
+        };
+
+        vec![
+            format!(
+                "{:.2} {} per op on normal operands, {:.2} on subnormal ones (median of {} trials)",
+                measurement.normal_per_op, TICK_UNIT, measurement.subnormal_per_op, self.trials
+            ),
+            format!(
+                "slowdown {:.2}x (MAD {:.2}), bucket {}",
+                measurement.ratio,
+                measurement.ratio_mad,
+                bucket_label(measurement.bucket)
+            ),
+        ]
+    }
+}
+
+/// The octave `ratio` falls in, see [`MAX_BUCKET`].
+pub fn bucket(ratio: f64) -> usize {
+    (ratio.max(1.0).log2().floor() as usize).min(MAX_BUCKET)
+}
+
+pub fn bucket_label(bucket: usize) -> String {
+    match bucket {
+        0 => "< 2x".to_string(),
+        MAX_BUCKET => format!(">= {}x", 1u64 << MAX_BUCKET),
+        _ => format!("{}x - {}x", 1u64 << bucket, 1u64 << (bucket + 1)),
+    }
+}
+
+/// Times `trials` alternating passes of the denormal step over `sample_size` normal and
+/// subnormal operands.
+pub fn measure_penalty(sample_size: usize, trials: usize) -> PenaltyMeasurement {
+    // x / 1.11 + x * 0.91 keeps these subnormal
+    let subnormal: Vec<f64> = (0..sample_size)
+        .map(|i| 1e-310 * (1.0 + i as f64 / sample_size as f64))
+        .collect();
+    let normal: Vec<f64> = subnormal.iter().map(|&x| x * NORMAL_SCALE).collect();
+    let mut out = vec![0.0; sample_size];
+
+    let ops = (sample_size * PASSES * OPS_PER_STEP) as f64;
+    let mut normal_costs = Vec::with_capacity(trials);
+    let mut subnormal_costs = Vec::with_capacity(trials);
+    let mut ratios = Vec::with_capacity(trials);
+
+    // one untimed pass each to warm up caches and branch predictors
+    time_passes(&normal, &mut out);
+    time_passes(&subnormal, &mut out);
+
+    for _ in 0..trials {
+        let normal_cost = time_passes(&normal, &mut out).max(1) as f64 / ops;
+        let subnormal_cost = time_passes(&subnormal, &mut out).max(1) as f64 / ops;
+
+        normal_costs.push(normal_cost);
+        subnormal_costs.push(subnormal_cost);
+        ratios.push(subnormal_cost / normal_cost);
+    }
+
+    let ratio = median(&mut ratios);
+    let mut deviations: Vec<f64> = ratios.iter().map(|r| (r - ratio).abs()).collect();
+
+    PenaltyMeasurement {
+        normal_per_op: median(&mut normal_costs),
+        subnormal_per_op: median(&mut subnormal_costs),
+        ratio,
+        ratio_mad: median(&mut deviations),
+        bucket: bucket(ratio),
+    }
+}
+
+fn time_passes(operands: &[f64], out: &mut [f64]) -> u64 {
+    let divisor = black_box(X_DIVISOR);
+    let factor = black_box(X_FACTOR);
+
+    let start = ticks();
+    for _ in 0..PASSES {
+        // a fresh opaque slice every pass so the passes can't be merged
+        step_pass(black_box(operands), out, divisor, factor);
+        black_box(&mut *out);
+    }
+    ticks().saturating_sub(start)
+}
+
+/// [`super::denormal::denormal_step`] over every operand, written out in assembly so the timed instructions
+/// don't depend on the build profile.
+#[cfg(target_arch = "x86_64")]
+#[inline(never)]
+fn step_pass(operands: &[f64], out: &mut [f64], divisor: f64, factor: f64) {
+    let len = operands.len().min(out.len());
+    if len == 0 {
+        return;
+    }
+
+    // SAFETY: the loop reads `len` f64s from `operands` and writes `len` f64s to `out`, both
+    // hold at least that many
+    unsafe {
+        std::arch::asm!(
+            "2:",
+            "movsd {x}, qword ptr [{src}]",
+            "movapd {q}, {x}",
+            "divsd {q}, {divisor}",
+            "mulsd {x}, {factor}",
+            "addsd {q}, {x}",
+            "movsd qword ptr [{dst}], {q}",
+            "add {src}, 8",
+            "add {dst}, 8",
+            "dec {n}",
+            "jnz 2b",
+            src = inout(reg) operands.as_ptr() => _,
+            dst = inout(reg) out.as_mut_ptr() => _,
+            n = inout(reg) len => _,
+            divisor = in(xmm_reg) divisor,
+            factor = in(xmm_reg) factor,
+            x = out(xmm_reg) _,
+            q = out(xmm_reg) _,
+            options(nostack),
+        );
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+#[inline(never)]
+fn step_pass(operands: &[f64], out: &mut [f64], divisor: f64, factor: f64) {
+    for (result, &x) in out.iter_mut().zip(operands) {
+        *result = denormal_step(x, divisor, factor);
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+
+    if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+fn ticks() -> u64 {
+    // SAFETY: RDTSC is available on every x86_64 CPU
+    unsafe { std::arch::x86_64::_rdtsc() }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn ticks() -> u64 {
+    use std::sync::OnceLock;
+    use std::time::Instant;
+
+    static EPOCH: OnceLock<Instant> = OnceLock::new();
+    EPOCH.get_or_init(Instant::now).elapsed().as_nanos() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets_are_octaves() {
+        assert_eq!(bucket(0.8), 0);
+        assert_eq!(bucket(1.99), 0);
+        assert_eq!(bucket(2.0), 1);
+        assert_eq!(bucket(63.9), 5);
+        assert_eq!(bucket(64.0), 6);
+        assert_eq!(bucket(1e6), MAX_BUCKET);
+    }
+
+    #[test]
+    fn earlier_runs_dont_move_the_bucket() {
+        let test = DenormalTimingTest::new(256, 5);
+        test.run().unwrap();
+        let measured = test.last_measurement().unwrap().bucket;
+
+        // what the FTZ/DAZ sweep leaves behind, and a CPU on either side of this one
+        for earlier in [
+            0,
+            measured.saturating_sub(1),
+            (measured + 1).min(MAX_BUCKET),
+        ] {
+            *test.last.lock().unwrap() = Some(PenaltyMeasurement {
+                normal_per_op: 1.0,
+                subnormal_per_op: 1.0,
+                ratio: (1u64 << earlier) as f64,
+                ratio_mad: 0.0,
+                bucket: earlier,
+            });
+
+            let results = test.run().unwrap();
+            let ratio = test.last_measurement().unwrap().ratio;
+
+            assert_eq!(results, [bucket(ratio) as f64]);
+        }
+    }
+
+    #[test]
+    fn labels() {
+        assert_eq!(bucket_label(0), "< 2x");
+        assert_eq!(bucket_label(6), "64x - 128x");
+        assert_eq!(bucket_label(MAX_BUCKET), ">= 256x");
+    }
+}
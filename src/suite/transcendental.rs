@@ -0,0 +1,207 @@
+use std::f64::consts::PI;
+use std::hint::black_box;
+
+use crate::SAMPLE_SIZE;
+use crate::error::Result;
+
+use crate::math::{Math, MathLibrary, PortableMath, SystemMath};
+
+use super::FingerprintTest;
+
+/// Feeds a fixed set of angles through the libm transcendental functions.
+pub struct TranscendentalFunctionTest {
+    /// Only reserves space for the results, the inputs are fixed.
+    pub sample_size: usize,
+    pub math: MathLibrary,
+}
+
+impl Default for TranscendentalFunctionTest {
+    fn default() -> Self {
+        Self {
+            sample_size: SAMPLE_SIZE,
+            math: MathLibrary::System,
+        }
+    }
+}
+
+impl FingerprintTest for TranscendentalFunctionTest {
+    fn id(&self) -> &'static str {
+        "transcendental"
+    }
+
+    fn name(&self) -> &'static str {
+        "Transcendental Function Test"
+    }
+
+    fn description(&self) -> &'static str {
+        "sin, cos, exp, sinh, cosh, log, atan, tanh and hypot over fixed angles"
+    }
+
+    fn version(&self) -> u32 {
+        2
+    }
+
+    fn run(&self) -> Result<Vec<f64>> {
+        Ok(match self.math {
+            MathLibrary::System => transcendental_function_test::<SystemMath>(self.sample_size),
+            MathLibrary::Portable => transcendental_function_test::<PortableMath>(self.sample_size),
+        })
+    }
+
+    fn with_math(&self, math: MathLibrary) -> Option<Box<dyn FingerprintTest>> {
+        Some(Box::new(Self {
+            sample_size: self.sample_size,
+            math,
+        }))
+    }
+
+    fn describe_result(&self, index: usize) -> Option<String> {
+        let val = test_values().get(index / QUANTITIES.len()).copied()?;
+
+        Some(format!(
+            "x = {:?} (test_values[{}]), {}",
+            val,
+            index / QUANTITIES.len(),
+            QUANTITIES[index % QUANTITIES.len()]
+        ))
+    }
+
+    fn sub_results(&self, results: &[f64]) -> Vec<(String, Vec<f64>)> {
+        let column = |offset: usize| results.iter().skip(offset).step_by(QUANTITIES.len());
+
+        let derived = QUANTITIES[..DERIVED_QUANTITIES]
+            .iter()
+            .enumerate()
+            .map(|(offset, &name)| (name.to_string(), column(offset).copied().collect()));
+
+        let libm_calls = LIBM_CALLS.iter().map(|(name, offsets)| {
+            let values = offsets.iter().flat_map(|&offset| column(offset)).copied();
+            (format!("libm:{}", name), values.collect())
+        });
+
+        derived.chain(libm_calls).collect()
+    }
+}
+
+/// Everything recorded for each test value, in the order it is pushed.
+///
+/// The first [`DERIVED_QUANTITIES`] are the combined results, the rest are libm calls that only
+/// appear inside a combination, recorded on their own so they get a sub-fingerprint.
+pub const QUANTITIES: [&str; 16] = [
+    "sin(x)",
+    "cos(x)",
+    "sin(10 sin(x))",
+    "exp(cos(x)) - 1",
+    "sinh(x) cosh(x) - sinh(2x) / 2",
+    "log10(|x| + 1) + log2(|x| + 2)",
+    "atan(x)",
+    "tanh(x)",
+    "hypot(sin(x), cos(x)) - 1",
+    "exp(cos(x))",
+    "sinh(x)",
+    "cosh(x)",
+    "sinh(2x)",
+    "log10(|x| + 1)",
+    "log2(|x| + 2)",
+    "hypot(sin(x), cos(x))",
+];
+
+pub const DERIVED_QUANTITIES: usize = 9;
+
+// Offsets into QUANTITIES holding the raw output of each libm function
+const LIBM_CALLS: [(&str, &[usize]); 10] = [
+    ("sin", &[0, 2]),
+    ("cos", &[1]),
+    ("exp", &[9]),
+    ("sinh", &[10, 12]),
+    ("cosh", &[11]),
+    ("log10", &[13]),
+    ("log2", &[14]),
+    ("atan", &[6]),
+    ("tanh", &[7]),
+    ("hypot", &[15]),
+];
+
+/// The inputs of [`transcendental_function_test`].
+#[allow(clippy::excessive_precision)]
+pub fn test_values() -> Vec<f64> {
+    let mut test_values = Vec::with_capacity(517);
+
+    test_values.extend_from_slice(&[
+        0.0,
+        1e-15,
+        PI / 6.0,
+        PI / 4.0,
+        PI / 3.0,
+        PI / 2.0,
+        PI,
+        3.0 * PI / 2.0,
+        2.0 * PI,
+        1.0,
+        -1.0,
+        0.5,
+        -0.534634634512312587,
+        1e-10,
+        -1e-10,
+        1e15,
+        -1e15,
+    ]);
+
+    for i in 0..500 {
+        test_values.push(i as f64 * PI / 17.12344658922222221111154657);
+    }
+
+    test_values
+}
+
+// This has appeared unique regardless of sample size
+#[inline(never)]
+pub fn transcendental_function_test<M: Math>(sample_size: usize) -> Vec<f64> {
+    let mut results = Vec::with_capacity(sample_size);
+
+    for val in test_values() {
+        // keeps LLVM from evaluating the whole test with the build host's libm
+        let val = black_box(val);
+
+        let sin_val = M::sin(val);
+        let cos_val = M::cos(val);
+
+        let sin_of_sin = M::sin(sin_val * 10.0);
+        let exp_cos = M::exp(cos_val);
+        let exp_of_cos = exp_cos - 1.0;
+
+        let sinh_val = M::sinh(val);
+        let cosh_val = M::cosh(val);
+        let sinh_2x = M::sinh(2.0 * val);
+        let compound1 = sinh_val * cosh_val - 0.5 * sinh_2x;
+
+        let log10_val = M::log10(val.abs() + 1.0);
+        let log2_val = M::log2(val.abs() + 2.0);
+        let compound2 = log10_val + log2_val;
+
+        let atan_val = M::atan(val);
+        let tanh_val = M::tanh(val);
+
+        results.push(sin_val);
+        results.push(cos_val);
+        results.push(sin_of_sin);
+        results.push(exp_of_cos);
+        results.push(compound1);
+        results.push(compound2);
+        results.push(atan_val);
+        results.push(tanh_val);
+
+        let hypot = M::hypot(sin_val, cos_val);
+        results.push(hypot - 1.0);
+
+        results.push(exp_cos);
+        results.push(sinh_val);
+        results.push(cosh_val);
+        results.push(sinh_2x);
+        results.push(log10_val);
+        results.push(log2_val);
+        results.push(hypot);
+    }
+
+    results
+}
@@ -0,0 +1,152 @@
+use std::arch::asm;
+use std::hint::black_box;
+
+use crate::error::Result;
+
+use super::transcendental::test_values;
+use super::{FingerprintTest, ResultKind};
+
+/// Runs the microcoded x87 transcendental instructions at 80-bit extended precision.
+pub struct X87Test;
+
+/// The instructions in the order they are recorded for each input.
+///
+/// Every result is the full 80-bit value, stored as two `f64`s: the 64-bit significand and the
+/// sign and exponent word, both as raw bits.
+pub const INSTRUCTIONS: [&str; 6] = [
+    "fsin(x)",
+    "fcos(x)",
+    "fptan(x)",
+    "fpatan(x, 1)",
+    "f2xm1(fract(x))",
+    "fyl2x(|x| + 1, 1)",
+];
+
+const VALUES_PER_RESULT: usize = 2;
+
+// Round to nearest, 64-bit significand, all exceptions masked (the FNINIT default)
+const EXTENDED_PRECISION: u16 = 0x037f;
+
+/// Runs the x87 instructions on `x` (available as `{x}`) with extended precision and returns
+/// the top of the stack as stored by `fstp tbyte`. The instructions must leave exactly one value
+/// on the stack.
+macro_rules! x87 {
+    ($x:expr, $($instruction:literal),+ $(,)?) => {{
+        let x: f64 = $x;
+        let control = EXTENDED_PRECISION;
+        let mut saved = 0u16;
+        let mut out = [0u8; 10];
+
+        // SAFETY: only reads and writes the locals above, restores the control word and leaves
+        // the x87 stack empty as the ABI requires
+        unsafe {
+            asm!(
+                "fnstcw word ptr [{saved}]",
+                "fldcw word ptr [{control}]",
+                $($instruction,)+
+                "fstp tbyte ptr [{out}]",
+                "fldcw word ptr [{saved}]",
+                x = in(reg) &x,
+                control = in(reg) &control,
+                saved = in(reg) &mut saved,
+                out = in(reg) &mut out,
+                out("st(0)") _, out("st(1)") _, out("st(2)") _, out("st(3)") _,
+                out("st(4)") _, out("st(5)") _, out("st(6)") _, out("st(7)") _,
+                options(nostack),
+            );
+        }
+
+        out
+    }};
+}
+
+impl FingerprintTest for X87Test {
+    fn id(&self) -> &'static str {
+        "x87"
+    }
+
+    fn name(&self) -> &'static str {
+        "x87 FPU Transcendental Test"
+    }
+
+    fn description(&self) -> &'static str {
+        "fsin, fcos, fptan, fpatan, f2xm1 and fyl2x at 80-bit precision on the transcendental inputs"
+    }
+
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn run(&self) -> Result<Vec<f64>> {
+        let values = black_box(test_values());
+        let mut results = Vec::with_capacity(values.len() * INSTRUCTIONS.len() * VALUES_PER_RESULT);
+
+        for val in values {
+            let extended = [
+                x87!(val, "fld qword ptr [{x}]", "fsin"),
+                x87!(val, "fld qword ptr [{x}]", "fcos"),
+                // fptan pushes 1.0 on top of the tangent
+                x87!(val, "fld qword ptr [{x}]", "fptan", "fstp st(0)"),
+                x87!(val, "fld qword ptr [{x}]", "fld1", "fpatan"),
+                x87!(val.fract(), "fld qword ptr [{x}]", "f2xm1"),
+                x87!(val.abs() + 1.0, "fld1", "fld qword ptr [{x}]", "fyl2x"),
+            ];
+
+            for bytes in extended {
+                results.extend(from_extended(bytes));
+            }
+        }
+
+        Ok(results)
+    }
+
+    fn describe_result(&self, index: usize) -> Option<String> {
+        let per_value = INSTRUCTIONS.len() * VALUES_PER_RESULT;
+        let val = test_values().get(index / per_value).copied()?;
+        let instruction = INSTRUCTIONS[index % per_value / VALUES_PER_RESULT];
+        let half = if index.is_multiple_of(VALUES_PER_RESULT) {
+            "significand"
+        } else {
+            "sign and exponent"
+        };
+
+        Some(format!("{} ({}) with x = {:e}", instruction, half, val))
+    }
+
+    fn result_kind(&self, _index: usize) -> ResultKind {
+        ResultKind::Bits
+    }
+
+    fn follows_mxcsr(&self) -> bool {
+        // the x87 FPU rounds by its own control word, which the test sets itself
+        false
+    }
+
+    fn sub_results(&self, results: &[f64]) -> Vec<(String, Vec<f64>)> {
+        let per_value = INSTRUCTIONS.len() * VALUES_PER_RESULT;
+
+        INSTRUCTIONS
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let offset = i * VALUES_PER_RESULT;
+                let values = results
+                    .chunks(per_value)
+                    .flat_map(|chunk| &chunk[offset..offset + VALUES_PER_RESULT])
+                    .copied();
+                (name.to_string(), values.collect())
+            })
+            .collect()
+    }
+}
+
+/// Splits an 80-bit value into its significand and sign/exponent word, as `f64` bit patterns.
+pub fn from_extended(bytes: [u8; 10]) -> [f64; 2] {
+    let significand = u64::from_le_bytes(bytes[..8].try_into().unwrap());
+    let sign_exponent = u16::from_le_bytes([bytes[8], bytes[9]]);
+
+    [
+        f64::from_bits(significand),
+        f64::from_bits(sign_exponent as u64),
+    ]
+}
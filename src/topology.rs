@@ -0,0 +1,224 @@
+//! Hardware topology from `/sys/devices/system/cpu` and `/sys/devices/system/node`.
+//!
+//! Unlike `num_cpus::get`, which follows cgroup limits and affinity masks, this describes every
+//! online CPU of the machine.
+
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::cpuid::CacheKind;
+
+const CPU_DIR: &str = "/sys/devices/system/cpu";
+const NODE_DIR: &str = "/sys/devices/system/node";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Topology {
+    pub sockets: usize,
+    /// Physical cores, counting SMT siblings once.
+    pub cores: usize,
+    /// Online logical CPUs.
+    pub threads: usize,
+    pub cpus: Vec<LogicalCpu>,
+    /// Empty on kernels without NUMA support.
+    pub numa_nodes: Vec<NumaNode>,
+    /// Cache levels as seen from the first online CPU, innermost first.
+    pub caches: Vec<CacheLevel>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogicalCpu {
+    pub cpu: usize,
+    /// Physical package id, `None` if the kernel doesn't report it.
+    pub socket: Option<u32>,
+    /// Core id within the socket, shared by SMT siblings.
+    pub core: Option<u32>,
+    /// CPUs sharing this CPU's core, including itself.
+    pub siblings: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NumaNode {
+    pub id: usize,
+    pub cpus: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheLevel {
+    pub level: u32,
+    pub kind: CacheKind,
+    /// In bytes.
+    pub size: u64,
+    pub ways: u32,
+    pub line_size: u32,
+    pub sets: u32,
+    /// Logical CPUs sharing one instance of this cache.
+    pub shared_by: usize,
+    /// Distinct instances across the online CPUs, e.g. one L3 per socket.
+    pub instances: usize,
+}
+
+impl Topology {
+    /// Reads the topology of the online CPUs, `None` outside Linux or without sysfs.
+    pub fn current() -> Option<Self> {
+        let online = parse_cpu_list(&read(&format!("{}/online", CPU_DIR))?)?;
+
+        let cpus: Vec<LogicalCpu> = online
+            .iter()
+            .map(|&cpu| LogicalCpu {
+                cpu,
+                socket: cpu_topology_id(cpu, "physical_package_id"),
+                core: cpu_topology_id(cpu, "core_id"),
+                siblings: read(&format!(
+                    "{}/cpu{}/topology/thread_siblings_list",
+                    CPU_DIR, cpu
+                ))
+                .and_then(|list| parse_cpu_list(&list))
+                .unwrap_or_else(|| vec![cpu]),
+            })
+            .collect();
+
+        let mut sockets: Vec<Option<u32>> = cpus.iter().map(|cpu| cpu.socket).collect();
+        sockets.sort_unstable();
+        sockets.dedup();
+
+        let mut cores: Vec<(Option<u32>, Option<u32>, usize)> = cpus
+            .iter()
+            .map(|cpu| (cpu.socket, cpu.core, cpu.siblings[0]))
+            .collect();
+        cores.sort_unstable();
+        cores.dedup();
+
+        Some(Self {
+            sockets: sockets.len(),
+            cores: cores.len(),
+            threads: cpus.len(),
+            caches: online
+                .first()
+                .map_or_else(Vec::new, |&cpu| caches(cpu, &online)),
+            cpus,
+            numa_nodes: numa_nodes(),
+        })
+    }
+
+    /// Threads per core, 1 without SMT.
+    pub fn threads_per_core(&self) -> usize {
+        self.threads / self.cores.max(1)
+    }
+}
+
+/// One of the numeric files in `cpuN/topology`, e.g. `core_id`.
+pub(crate) fn cpu_topology_id(cpu: usize, name: &str) -> Option<u32> {
+    read(&format!("{}/cpu{}/topology/{}", CPU_DIR, cpu, name))?
+        .parse()
+        .ok()
+}
+
+fn numa_nodes() -> Vec<NumaNode> {
+    let Some(online) = read(&format!("{}/online", NODE_DIR)).and_then(|list| parse_cpu_list(&list))
+    else {
+        return Vec::new();
+    };
+
+    online
+        .into_iter()
+        .filter_map(|id| {
+            let cpus = parse_cpu_list(&read(&format!("{}/node{}/cpulist", NODE_DIR, id))?)?;
+            Some(NumaNode { id, cpus })
+        })
+        .collect()
+}
+
+fn caches(cpu: usize, online: &[usize]) -> Vec<CacheLevel> {
+    let mut caches = Vec::new();
+
+    for index in 0.. {
+        let dir = format!("{}/cpu{}/cache/index{}", CPU_DIR, cpu, index);
+        if !Path::new(&dir).exists() {
+            break;
+        }
+
+        let field = |name: &str| read(&format!("{}/{}", dir, name));
+        let number = |name: &str| field(name).and_then(|value| value.parse().ok());
+
+        let kind = match field("type").as_deref() {
+            Some("Data") => CacheKind::Data,
+            Some("Instruction") => CacheKind::Instruction,
+            Some("Unified") => CacheKind::Unified,
+            _ => continue,
+        };
+        let Some(level) = number("level") else {
+            continue;
+        };
+
+        let shared = field("shared_cpu_list")
+            .and_then(|list| parse_cpu_list(&list))
+            .unwrap_or_else(|| vec![cpu]);
+
+        // every CPU has an entry with the same index for the same cache
+        let mut instances: Vec<Vec<usize>> = online
+            .iter()
+            .filter_map(|other| {
+                read(&format!(
+                    "{}/cpu{}/cache/index{}/shared_cpu_list",
+                    CPU_DIR, other, index
+                ))
+                .and_then(|list| parse_cpu_list(&list))
+            })
+            .collect();
+        instances.sort_unstable();
+        instances.dedup();
+
+        caches.push(CacheLevel {
+            level,
+            kind,
+            size: field("size")
+                .and_then(|size| parse_size(&size))
+                .unwrap_or(0),
+            ways: number("ways_of_associativity").unwrap_or(0),
+            line_size: number("coherency_line_size").unwrap_or(0),
+            sets: number("number_of_sets").unwrap_or(0),
+            shared_by: shared.len(),
+            instances: instances.len().max(1),
+        });
+    }
+
+    caches
+}
+
+/// Parses the kernel's CPU list format, e.g. `"0-3,8,10-11"`.
+pub fn parse_cpu_list(list: &str) -> Option<Vec<usize>> {
+    let mut cpus = Vec::new();
+
+    for range in list.trim().split(',').filter(|range| !range.is_empty()) {
+        match range.split_once('-') {
+            Some((first, last)) => cpus.extend(first.parse::<usize>().ok()?..=last.parse().ok()?),
+            None => cpus.push(range.parse().ok()?),
+        }
+    }
+
+    Some(cpus)
+}
+
+/// Parses cache sizes like `"48K"`, `"2048K"` or `"1M"`.
+fn parse_size(size: &str) -> Option<u64> {
+    let (digits, unit) = match size.find(|c: char| !c.is_ascii_digit()) {
+        Some(at) => size.split_at(at),
+        None => (size, ""),
+    };
+
+    let multiplier = match unit {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return None,
+    };
+
+    Some(digits.parse::<u64>().ok()? * multiplier)
+}
+
+fn read(path: &str) -> Option<String> {
+    Some(fs::read_to_string(path).ok()?.trim().to_string())
+}